@@ -1,33 +1,147 @@
 use crate::config::Config;
+use crate::database::{Completion, Database};
 use crate::task::Task;
+use crate::task_rand_config::{AfterSleep, AutoAdvance, TaskRandConfig};
 use crate::taskwarrior::Taskwarrior;
-use anyhow::{Context, Result};
-use chrono::{DateTime, Duration, Local, Utc};
-use crossterm::event::{Event, KeyCode, KeyModifiers};
+use crate::timewarrior::Timewarrior;
+use crate::weight::WeightContext;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Local, NaiveTime, Offset, TimeZone, Utc};
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 use itertools::Itertools;
 use rand::prelude::*;
 use ratatui::{
-    layout::{Constraint, Flex, Layout},
+    layout::{Constraint, Flex, Layout, Rect},
     style::{palette::tailwind, Style, Stylize},
+    symbols::border,
     text::{Line, Span, Text},
-    widgets::{Gauge, Paragraph, Wrap},
+    widgets::{Block, Clear, Gauge, Paragraph, Wrap},
     Frame,
 };
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use tokio::process::Command;
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Debug)]
 pub struct App {
     tw: Taskwarrior,
     config: Config,
+    settings: TaskRandConfig,
+
+    /// Extra Taskwarrior filters for the pool, on top of the ones we always
+    /// use.
+    filters: Vec<String>,
+
+    /// Weight the oldest tasks most heavily, for a session of clearing out
+    /// stale tasks.
+    stale: bool,
+
+    /// Draw with plain ASCII, for terminals and fonts that mangle anything
+    /// fancier.
+    ascii: bool,
+
+    /// Only pick tasks in this project (or its subprojects), chosen with `P`.
+    project: Option<String>,
 
     /// This is the thing we're doing *right now*
     doing: Activity,
 
+    /// Exporting from Taskwarrior can be slow on large databases, so we keep
+    /// the last export around for `settings.cache_ttl` to make rerolls snappy.
+    pool: Option<Pool>,
+
+    /// How many tasks were in the pool before we last invalidated it, so the
+    /// status line has something to show until we fetch it again.
+    last_ready_count: Option<usize>,
+
+    /// Where we keep a long-term record of completed tasks, if anywhere.
+    database: Option<Database>,
+
+    /// Where to track time while we work on tasks, if anywhere.
+    timewarrior: Option<Timewarrior>,
+
+    /// The uuid of the task Timewarrior is tracking time for right now.
+    tracking: Option<String>,
+
+    /// The uuid of the task we last counted dependents for, and the count.
+    /// Tasks come out of the pool without one, so this carries it over if
+    /// we pick the same task again.
+    blocking_counted: Option<(String, usize)>,
+
+    /// When the timer we last sent a notification for ran out, so we only
+    /// notify once per timer (but again if it's extended.)
+    notified: Option<DateTime<Utc>>,
+
+    /// Where our randomness comes from. This can be seeded to make picks
+    /// reproducible.
+    rng: StdRng,
+
+    /// When we last picked each task this session, so we can avoid showing
+    /// the same few tasks over and over.
+    recently_shown: HashMap<String, DateTime<Utc>>,
+
+    /// When we skipped each task this session. These come back even more
+    /// slowly than tasks we've just shown.
+    skipped: HashMap<String, DateTime<Utc>>,
+
+    /// How long we've spent on each task this session before now, and over
+    /// how many visits.
+    time_spent: HashMap<String, (Duration, u32)>,
+
+    /// When we last rerolled, for `settings.reroll_cooldown`.
+    last_reroll: Option<DateTime<Utc>>,
+
+    /// The project of the last task we picked, for `settings.spread_projects`.
+    last_project: Option<String>,
+
+    /// The uuid of the task we were on before this one, so we can go back to
+    /// it after an accidental reroll.
+    previous: Option<String>,
+
+    /// How each task we've had this session ended, oldest first.
+    history: Vec<Outcome>,
+
+    /// When this session began, for working out our pace.
+    session_started: DateTime<Utc>,
+
+    /// When we last handled a tick, so we can tell when the computer slept.
+    last_tick: Option<DateTime<Utc>>,
+
+    /// How often the main loop calls `handle_tick`.
+    poll_interval: Duration,
+
+    /// Why nothing is ready, for `settings.explain_nothing`. We keep this
+    /// for `settings.cache_ttl` so we don't ask Taskwarrior every tick.
+    not_ready: Option<NotReady>,
+
+    /// How many tasks in a row we've completed without rerolling or waiting.
+    streak: u32,
+
+    /// Where we last drew the status line, so we can tell what a mouse click
+    /// landed on.
+    status_line_area: Cell<Rect>,
+
+    /// A short note about the last thing we did, shown in place of the key
+    /// hints until the next key press.
+    message: Option<String>,
+
+    /// Something drawn on top of the main screen that takes over input until
+    /// it's dismissed.
+    overlay: Option<Overlay>,
+
     /// If we need to do interactive work (e.g. editing a task) we need to get
     /// out of the interactive terminal temporarily. We signal to the main loop
     /// that we need to do this by setting this field to `Some(Command)`. The
-    /// main loop will run the command and then set this field back to `None`.
-    interactive: Option<Command>,
+    /// main loop will run the command, set this field back to `None`, and then
+    /// refresh as the command asked.
+    interactive: Option<(Command, Refresh)>,
+
+    /// Whether to stay on the nothing screen instead of picking a task on the
+    /// next tick, after finishing a task with `D`.
+    holding: bool,
 
     /// The main loop uses this as a signal that it should exit.
     should_quit: bool,
@@ -38,48 +152,154 @@ impl App {
         Self {
             tw,
             config,
+            settings: TaskRandConfig::default(),
+            filters: Vec::new(),
+            stale: false,
+            ascii: false,
+            project: None,
 
             doing: Activity::Nothing,
+            pool: None,
+            last_ready_count: None,
+            database: None,
+            timewarrior: None,
+            tracking: None,
+            blocking_counted: None,
+            notified: None,
+            rng: StdRng::from_os_rng(),
+            recently_shown: HashMap::new(),
+            skipped: HashMap::new(),
+            time_spent: HashMap::new(),
+            last_reroll: None,
+            last_project: None,
+            previous: None,
+            history: Vec::new(),
+            session_started: Utc::now(),
+            last_tick: None,
+            poll_interval: Duration::seconds(1),
+            not_ready: None,
+            streak: 0,
+            status_line_area: Cell::new(Rect::default()),
+            message: None,
+            overlay: None,
             interactive: None,
+            holding: false,
             should_quit: false,
         }
     }
 
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        if let Some(seed) = seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+
+        self
+    }
+
+    pub fn with_database(mut self, database: Option<Database>) -> Self {
+        self.database = database;
+
+        self
+    }
+
+    pub fn with_settings(mut self, settings: TaskRandConfig) -> Self {
+        self.settings = settings;
+
+        self
+    }
+
+    pub fn with_filters(mut self, filters: Vec<String>) -> Self {
+        self.filters = filters;
+
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+
+        self
+    }
+
+    pub fn with_ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+
+        self
+    }
+
+    pub fn with_stale(mut self, stale: bool) -> Self {
+        self.stale = stale;
+
+        self
+    }
+
+    pub fn with_timewarrior(mut self, timewarrior: Option<Timewarrior>) -> Self {
+        self.timewarrior = timewarrior;
+
+        self
+    }
+
     pub fn render(&self, frame: &mut Frame) {
-        let [app_area, status_line_area] =
-            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        self.render_at(frame, Utc::now());
+    }
+
+    /// Render as of `now`, so tests can check exactly what comes out.
+    fn render_at(&self, frame: &mut Frame, now: DateTime<Utc>) {
+        let [app_area, history_area, status_line_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(if self.history.is_empty() { 0 } else { 1 }),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
 
-        let app_box_vert = Layout::vertical([Constraint::Length(7)]).flex(Flex::Center);
-        let app_box_horiz = Layout::horizontal([Constraint::Percentage(75)]).flex(Flex::Center);
+        let [clock_area, _] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(app_area);
 
-        let [app_box_area] = app_box_vert.areas(app_area);
-        let [app_box_area] = app_box_horiz.areas(app_box_area);
+        frame.render_widget(
+            Line::from(
+                now.with_timezone(&Local)
+                    .format(self.settings.clock.time_format())
+                    .to_string(),
+            )
+            .right_aligned()
+            .dim(),
+            clock_area,
+        );
 
         let (title, gauge) = match &self.doing {
             Activity::Nothing => (
-                Paragraph::new(Text::from("Nothing to do right now")),
+                self.nothing_ready_line(),
                 Gauge::default()
                     .label("0:00")
-                    .ratio(100.0)
-                    .use_unicode(true),
+                    .ratio(1.0)
+                    .use_unicode(!self.ascii),
             ),
             Activity::Task {
                 task,
                 started,
                 length,
+                extensions,
+                overridden,
                 ..
             } => {
-                let time_remaining = *length - (Utc::now() - started);
+                let time_remaining = *length - (now - started);
+
+                let percent_elapsed = elapsed_ratio(time_remaining, *length);
+
+                let mut sections = Vec::new();
 
-                let percent_elapsed = 1.0
-                    - (time_remaining.num_seconds() as f64 / length.num_seconds() as f64)
-                        .clamp(0.0, 1.0);
+                if let Some(priority) = &task.priority {
+                    sections.push(Span::styled(
+                        format!(" {} ", priority),
+                        priority_style(priority),
+                    ));
+                    sections.push(Span::from(" "));
+                }
 
-                let mut sections = vec![Span::from(format!("{}", task.id)).bold()];
+                sections.push(Span::from(format!("{}", task.id)).bold());
 
-                if let Some(jira) = &task.jira {
+                if let Some(ticket) = task.uda(&self.settings.ticket_uda) {
                     sections.push(Span::from(" / "));
-                    sections.push(Span::from(jira).bold());
+                    sections.push(Span::from(ticket).bold());
                 }
 
                 sections.push(Span::from(":").bold());
@@ -101,22 +321,11 @@ impl App {
                 }
 
                 if let Some(due) = &task.due {
-                    let remaining = *due - Utc::now();
-
-                    let remaining_display = if remaining.num_seconds().abs() < 60 {
-                        format!("{}s", remaining.num_seconds())
-                    } else if remaining.num_minutes().abs() < 60 {
-                        format!("{}m", remaining.num_minutes())
-                    } else if remaining.num_hours().abs() < 24 {
-                        format!("{}h", remaining.num_hours())
-                    } else if remaining.num_days().abs() < 14 {
-                        format!("{}d", remaining.num_days())
-                    } else {
-                        due.format("%Y-%m-%d").to_string()
-                    };
+                    let remaining_display =
+                        format_due(*due, now, &self.settings.date_format, &Local);
 
-                    let remaining_style = if remaining.num_seconds() < 0 {
-                        Style::default().fg(tailwind::RED.c800)
+                    let remaining_style = if *due < now {
+                        self.settings.theme.overdue
                     } else {
                         Style::default()
                     };
@@ -130,300 +339,3933 @@ impl App {
                     sections.push(Span::from(" [A]").bold());
                 }
 
-                sections.push(Span::from(" "));
-                sections.push(
-                    Span::from(format!(
-                        "until {}",
-                        (*started + *length)
-                            .with_timezone(&Local)
-                            .format("%-I:%M %P")
-                    ))
-                    .italic()
-                    .dim(),
-                );
+                if task.blocking > 0 {
+                    sections.push(
+                        Span::from(format!(" {}blocks {}", self.glyph("⛓ ", ""), task.blocking))
+                            .bold(),
+                    );
+                }
+
+                if let Some(end) = self.doing.ends_at() {
+                    sections.push(Span::from(" "));
+                    sections.push(
+                        Span::from(format!(
+                            "until {}",
+                            end.with_timezone(&Local)
+                                .format(self.settings.clock.time_format())
+                        ))
+                        .italic()
+                        .dim(),
+                    );
+                }
+
+                if *overridden {
+                    sections.push(Span::from(" (set)").dim());
+                }
+
+                if *extensions > 0 {
+                    sections.push(Span::from(format!(" (+{})", extensions)).dim());
+                }
 
                 (
-                    Paragraph::new(Line::from(sections))
-                        .centered()
-                        .wrap(Wrap { trim: false }),
+                    Line::from(sections),
                     Gauge::default()
                         .label(format_remaining(time_remaining))
-                        .gauge_style(gauge_style(time_remaining < Duration::zero()))
+                        .gauge_style(
+                            self.settings
+                                .theme
+                                .gauge_style(time_remaining < Duration::zero()),
+                        )
                         .ratio(percent_elapsed)
-                        .use_unicode(true),
+                        .use_unicode(!self.ascii),
                 )
             }
         };
 
+        // Size the box to fit the header once it's wrapped, so long (or wide)
+        // descriptions don't get cut off.
+        let [app_box_area] = Layout::horizontal([Constraint::Percentage(75)])
+            .flex(Flex::Center)
+            .areas(app_area);
+        let title_height = wrapped_height(&title, app_box_area.width).max(4);
+        let [app_box_area] = Layout::vertical([Constraint::Length(title_height + 3)])
+            .flex(Flex::Center)
+            .areas(app_box_area);
+
         let [title_area, gauge_area] =
             Layout::vertical([Constraint::Fill(1), Constraint::Length(3)]).areas(app_box_area);
 
-        frame.render_widget(title, title_area);
+        frame.render_widget(
+            Paragraph::new(title).centered().wrap(Wrap { trim: false }),
+            title_area,
+        );
         frame.render_widget(gauge, gauge_area);
 
+        if self.settings.flash_overtime {
+            if let Some(overtime) = self.doing.overtime(now) {
+                if flash_is_on(overtime) {
+                    frame
+                        .buffer_mut()
+                        .set_style(app_box_area, Style::new().reversed());
+                }
+            }
+        }
+
+        frame.render_widget(self.history_line(), history_area);
+
+        self.status_line_area.set(status_line_area);
+
+        let status_line = self
+            .status_line()
+            .into_iter()
+            .map(|(span, _)| span)
+            .collect_vec();
+
         frame.render_widget(
-            Line::from(vec![
-                Span::from("d").bold(),
-                Span::from("one "),
-                Span::from("e").bold(),
-                Span::from("dit "),
-                Span::from("f").bold(),
-                Span::from("ocus "),
-                Span::from("m").bold(),
-                Span::from("ore time "),
-                Span::from("r").bold(),
-                Span::from("eroll "),
-                Span::from("q").bold(),
-                Span::from("uit "),
-                Span::from("w").bold(),
-                Span::from("ait 1h "),
-                // TODO: these could be sourced from config
-                Span::from("o").bold(),
-                Span::from("pen "),
-                Span::from("b").bold(),
-                Span::from("reakdown"),
-            ])
-            .centered()
-            .style(gauge_style(false).reversed()),
+            Line::from(status_line)
+                .centered()
+                .style(self.settings.theme.gauge_style(false).reversed()),
             status_line_area,
         );
+
+        if let Some(overlay) = &self.overlay {
+            self.render_overlay(frame, overlay);
+        }
     }
 
-    pub async fn handle_input(&mut self, event: Event) -> Result<()> {
-        if let Event::Key(key_event) = event {
-            match key_event.code {
-                KeyCode::Char('q') => {
-                    self.should_quit = true;
-                }
-                KeyCode::Char('d') => {
-                    self.doing.mark_done(&self.tw).await?;
+    /// Explain why there's nothing to do, so it's clear whether that's
+    /// because everything is done or because of how we're filtering.
+    fn nothing_ready_line(&self) -> Line<'_> {
+        if self.holding {
+            return Line::from(vec![
+                Span::from("Done! ").bold(),
+                Span::from("Press "),
+                Span::styled("r", self.settings.theme.accent).bold(),
+                Span::from(" when you're ready for the next task."),
+            ])
+            .left_aligned();
+        }
 
-                    self.doing = self.choose_next_task().await?;
-                }
-                KeyCode::Char('r') => {
-                    self.doing = self.choose_next_task().await?;
-                }
-                KeyCode::Char('m') => {
-                    self.doing.extend();
-                }
-                KeyCode::Char('e') => {
-                    if let Activity::Task { task, .. } = &self.doing {
-                        let mut command = Command::new(&self.tw.binary);
-                        command.arg(&task.uuid);
-                        command.arg("edit");
+        let mut scope = self.pool_filters();
 
-                        self.interactive = Some(command)
-                    };
-                }
-                KeyCode::Char('w') => {
-                    if let Activity::Task { task, .. } = &self.doing {
-                        let mod_ = if key_event.modifiers.contains(KeyModifiers::SHIFT) {
-                            "wait:tomorrow"
-                        } else {
-                            "wait:1h"
-                        };
+        if let Some(context) = &self.tw.context {
+            scope.push(format!("(context {})", context));
+        }
 
-                        self.tw
-                            .modify()
-                            .with_subject(&task.uuid)
-                            .with_mod(mod_)
-                            .call()
-                            .await
-                            .with_context(|| format!("could not modify task {}", task.id))?;
+        if self.settings.hide_scheduled {
+            scope.push("(hiding scheduled tasks)".to_owned());
+        }
 
-                        self.doing = self.choose_next_task().await?;
-                    };
-                }
+        if let Some(uda) = &self.settings.not_before_uda {
+            scope.push(format!("(hiding tasks before their {})", uda));
+        }
 
-                // TODO: source these from config
-                KeyCode::Char('o') => {
-                    if let Activity::Task { task, .. } = &self.doing {
-                        let mut command = Command::new("tw-open");
-                        command.arg(&task.uuid);
-                        command.status().await.context("could not call tw-open")?;
-                    };
-                }
-                KeyCode::Char('b') => {
-                    if let Activity::Task { task, .. } = &self.doing {
-                        let mut command = Command::new("tw-breakdown");
+        let mut line = vec![
+            Span::from("Nothing to do right now. ").bold(),
+            Span::from("No tasks match "),
+            Span::from(scope.join(" ")).italic(),
+            Span::from(". "),
+        ];
 
-                        if !key_event.modifiers.contains(KeyModifiers::SHIFT) {
-                            command.arg("--seq");
-                        };
+        if let Some(summary) = self.not_ready.as_ref().and_then(NotReady::summary) {
+            line.push(Span::from(format!("{}. ", summary)));
+        }
 
-                        command.arg(&task.uuid);
+        line.extend([
+            Span::from("Press "),
+            Span::styled("r", self.settings.theme.accent).bold(),
+            Span::from(" to look again."),
+        ]);
 
-                        self.interactive = Some(command)
-                    };
+        Line::from(line).left_aligned()
+    }
+
+    /// `unicode`, or `ascii` if we're sticking to ASCII.
+    fn glyph(&self, unicode: &'static str, ascii: &'static str) -> &'static str {
+        if self.ascii {
+            ascii
+        } else {
+            unicode
+        }
+    }
+
+    /// A dot for each of the last few tasks this session: filled in if we
+    /// finished it, hollow if we moved on without finishing.
+    fn history_line(&self) -> Line<'_> {
+        let skip = self.history.len().saturating_sub(HISTORY_LENGTH);
+
+        Line::from(
+            self.history
+                .iter()
+                .skip(skip)
+                .map(|outcome| match outcome {
+                    Outcome::Done => {
+                        Span::styled(self.glyph("● ", "* "), self.settings.theme.accent).bold()
+                    }
+                    Outcome::Left => Span::from(self.glyph("○ ", "o ")).dim(),
+                })
+                .collect_vec(),
+        )
+        .centered()
+    }
+
+    /// The pieces of the status line, along with the key that clicking on
+    /// each one should press.
+    fn status_line(&self) -> Vec<(Span<'_>, Option<KeyEvent>)> {
+        let mut status_line = Vec::new();
+
+        if let Some(message) = &self.message {
+            status_line.push((Span::from(format!("{} ", message)).italic(), None));
+        }
+
+        let items = self
+            .settings
+            .status_line
+            .clone()
+            .unwrap_or_else(default_status_line);
+
+        for item in &items {
+            match item.as_str() {
+                "stale" => {
+                    if self.stale {
+                        status_line.push((
+                            Span::from(format!("{} stale mode ", self.glyph("·", "|"))).italic(),
+                            Some(KeyEvent::from(KeyCode::Char('a'))),
+                        ));
+                    }
                 }
-                KeyCode::Char('f') => {
-                    if let Activity::Task {
-                        task,
-                        started,
-                        length,
-                        ..
-                    } = &self.doing
-                    {
-                        let remaining_seconds = (*length - (Utc::now() - started)).num_seconds();
-
-                        if remaining_seconds > 0 {
-                            open::that(format!(
-                                "raycast://focus/start?goal={}&categories=messaging,social,news&duration={}",
-                                urlencoding::encode(&task.description),
-                                remaining_seconds,
+                "streak" => {
+                    if self.streak > 0 {
+                        status_line.push((
+                            Span::from(format!(
+                                "{} {} {} ",
+                                self.glyph("·", "|"),
+                                self.glyph("🔥", "streak"),
+                                self.streak
                             ))
-                            .context("could not start focus session")?;
+                            .bold(),
+                            None,
+                        ));
+                    }
+                }
+                "ready" => {
+                    status_line.push((
+                        Span::from(format!(
+                            "{} {} ready ",
+                            self.glyph("·", "|"),
+                            self.ready_count()
+                                .map_or_else(|| "?".to_owned(), |count| count.to_string())
+                        ))
+                        .dim(),
+                        None,
+                    ));
+                }
+                "clock" => {
+                    status_line.push((
+                        Span::from(format!(
+                            "{} {} ",
+                            self.glyph("·", "|"),
+                            Local::now().format(self.settings.clock.time_format())
+                        ))
+                        .dim(),
+                        None,
+                    ));
+                }
+                key => {
+                    // Key hints make way for the message.
+                    if self.message.is_some() {
+                        continue;
+                    }
+
+                    let Some(binding) = KEYS.iter().find(|binding| binding.key == key) else {
+                        continue;
+                    };
+
+                    let code = binding.key_event();
+                    let key = Span::styled(binding.key, self.settings.theme.accent).bold();
+
+                    match binding.label.strip_prefix(binding.key) {
+                        Some(rest) => {
+                            status_line.push((key, code));
+                            status_line.push((Span::from(format!("{} ", rest)), code));
+                        }
+                        None => {
+                            status_line.push((key, code));
+                            status_line.push((Span::from(format!(" {} ", binding.label)), code));
                         }
                     }
                 }
-                _ => {}
             }
         }
 
-        Ok(())
+        status_line
     }
 
-    pub async fn handle_tick(&mut self) -> Result<()> {
-        if self.doing.is_nothing() {
-            self.doing = self
-                .choose_next_task()
-                .await
-                .context("could not set a task")?;
-        }
+    fn render_overlay(&self, frame: &mut Frame, overlay: &Overlay) {
+        let (title, body) = match overlay {
+            Overlay::ConfirmDone { .. } => {
+                let description = match &self.doing {
+                    Activity::Task { task, .. } => task.description.as_str(),
+                    Activity::Nothing => "this task",
+                };
 
-        Ok(())
-    }
+                (
+                    " Mark done? ",
+                    Text::from(vec![
+                        Line::from(description),
+                        Line::from(""),
+                        Line::from(vec![
+                            Span::from("y").bold(),
+                            Span::from("es / "),
+                            Span::from("n").bold(),
+                            Span::from("o"),
+                        ]),
+                    ]),
+                )
+            }
+            Overlay::UpdateEstimate {
+                description,
+                actual,
+                ..
+            } => (
+                " Update estimate? ",
+                Text::from(vec![
+                    Line::from(description.as_str()),
+                    Line::from(""),
+                    Line::from(format!(
+                        "That took {}. Set its {} to match?",
+                        format_hours_minutes(*actual),
+                        self.settings.estimate_uda
+                    )),
+                    Line::from(""),
+                    Line::from(vec![
+                        Span::from("y").bold(),
+                        Span::from("es / "),
+                        Span::from("n").bold(),
+                        Span::from("o"),
+                    ]),
+                ]),
+            ),
+            Overlay::Help => (
+                " Keys ",
+                Text::from(
+                    KEYS.iter()
+                        .map(|binding| {
+                            Line::from(vec![
+                                Span::styled(
+                                    format!("{:>6}", binding.key),
+                                    self.settings.theme.accent,
+                                )
+                                .bold(),
+                                Span::from("  "),
+                                Span::from(binding.description),
+                            ])
+                            .left_aligned()
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            Overlay::Prompt { prompt, input } => {
+                let title = match prompt {
+                    Prompt::DoneNote { .. } => " Completion note ",
+                    Prompt::Tag => " Tags (+add -remove) ",
+                    Prompt::Search => " Search descriptions ",
+                    Prompt::Length => " Work for how long? (e.g. 40min, 1h) ",
+                    Prompt::Until => " Work until when? (e.g. 15:00, 3pm) ",
+                };
 
-    async fn available_tasks(&self) -> Result<Vec<Task>> {
-        self.tw
-            .export()
-            .with_urgency_coefficient("blocked", 0.0)
-            .with_urgency_coefficient("blocking", 0.0)
-            .with_filter("jirastatus.not:backlog")
-            .with_filter("+READY")
-            .call()
-            .await
-            .context("could not get tasks")
-    }
+                (
+                    title,
+                    Text::from(vec![
+                        Line::from(vec![
+                            Span::from(input),
+                            Span::from(self.glyph("▏", "_")).slow_blink(),
+                        ])
+                        .left_aligned(),
+                        Line::from(""),
+                        Line::from("enter to submit, esc to cancel").dim(),
+                    ]),
+                )
+            }
+            Overlay::Pick { tasks, selected } => {
+                let mut lines = tasks
+                    .iter()
+                    .enumerate()
+                    .map(|(index, task)| {
+                        let line = Line::from(vec![
+                            Span::from(format!("{:>5}  ", task.id)).bold(),
+                            Span::from(task.description.as_str()),
+                        ])
+                        .left_aligned();
 
-    async fn choose_next_task(&self) -> Result<Activity> {
-        let now = Utc::now();
+                        if index == *selected {
+                            line.reversed()
+                        } else {
+                            line
+                        }
+                    })
+                    .collect_vec();
 
-        let target_duration = Duration::minutes(25);
+                lines.push(Line::from(""));
+                lines.push(
+                    Line::from(format!(
+                        "{} to move, enter to start, esc to cancel",
+                        self.glyph("↑/↓", "up/down")
+                    ))
+                    .dim(),
+                );
 
-        let tasks = self.available_tasks().await?;
+                (" Matching tasks ", Text::from(lines))
+            }
+            Overlay::Project {
+                projects,
+                input,
+                selected,
+            } => {
+                let mut lines = vec![
+                    Line::from(vec![
+                        Span::from(input),
+                        Span::from(self.glyph("▏", "_")).slow_blink(),
+                    ])
+                    .left_aligned(),
+                    Line::from(""),
+                ];
 
-        let task = tasks
-            .choose_weighted(&mut rand::rng(), |task| {
-                task.urgency_at(now, &self.config).max(0.0)
-            })
-            .context("could not choose a task")?;
+                lines.extend(
+                    project_matches(projects, input)
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, project)| {
+                            let line = match project {
+                                Some(project) => Line::from(project),
+                                None => Line::from("every project").italic(),
+                            }
+                            .left_aligned();
 
-        let length = task
-            .estimate
-            .unwrap_or(target_duration)
-            .min(target_duration);
+                            if index == *selected {
+                                line.reversed()
+                            } else {
+                                line
+                            }
+                        }),
+                );
 
-        Ok(Activity::Task {
-            task: task.clone(),
-            started: now,
-            length,
-            original_length: length,
-        })
-    }
+                lines.push(Line::from(""));
+                lines.push(
+                    Line::from(format!(
+                        "type to search, {} to move, enter to choose, esc to cancel",
+                        self.glyph("↑/↓", "up/down")
+                    ))
+                    .dim(),
+                );
 
-    pub fn should_quit(&self) -> bool {
-        self.should_quit
-    }
+                (" Only pick from project ", Text::from(lines))
+            }
+            Overlay::Details { selected } => {
+                let Activity::Task { task, .. } = &self.doing else {
+                    return;
+                };
 
-    pub fn take_interactive(&mut self) -> Option<Command> {
-        self.interactive.take()
-    }
+                let date = |datetime: Option<DateTime<Utc>>| {
+                    datetime.map(|datetime| self.format_datetime(datetime))
+                };
 
-    pub async fn refresh_doing(&mut self) -> Result<()> {
-        self.doing.refresh_task(&self.tw).await
-    }
-}
+                let mut tags = task
+                    .tags
+                    .iter()
+                    .map(|tag| format!("+{}", tag))
+                    .collect_vec();
+                tags.sort();
 
-#[expect(
-    clippy::large_enum_variant,
-    reason = "only one is used at a time; it does not dominate memory usage"
-)]
-#[derive(Debug)]
-pub enum Activity {
-    Nothing,
-    Task {
-        task: Task,
-        started: DateTime<Utc>,
-        length: Duration,
-        original_length: Duration,
-    },
-}
+                let fields = [
+                    ("id", Some(task.id.to_string())),
+                    ("uuid", Some(task.uuid.clone())),
+                    ("description", Some(task.description.clone())),
+                    ("project", task.project.clone()),
+                    ("tags", Some(tags.join(" ")).filter(|tags| !tags.is_empty())),
+                    ("priority", task.priority.clone()),
+                    (
+                        self.settings.ticket_uda.as_str(),
+                        task.uda(&self.settings.ticket_uda).map(str::to_owned),
+                    ),
+                    ("entry", date(Some(task.entry))),
+                    ("due", date(task.due)),
+                    ("scheduled", date(task.scheduled)),
+                    ("wait", date(task.wait)),
+                    (
+                        self.settings.estimate_uda.as_str(),
+                        task.estimate(&self.settings.estimate_uda)
+                            .map(format_hours_minutes),
+                    ),
+                    ("urgency", Some(format!("{:.2}", task.urgency))),
+                ];
 
-impl Activity {
-    pub fn is_nothing(&self) -> bool {
-        matches!(self, Self::Nothing)
-    }
+                let mut lines = fields
+                    .into_iter()
+                    .map(|(name, value)| {
+                        Line::from(vec![
+                            Span::from(format!("{:>12}  ", name)).bold(),
+                            Span::from(value.unwrap_or_else(|| self.glyph("—", "-").to_owned())),
+                        ])
+                        .left_aligned()
+                    })
+                    .collect_vec();
 
-    pub async fn mark_done(&self, tw: &Taskwarrior) -> Result<()> {
-        if let Self::Task { task, .. } = self {
-            tw.mark_done(&task.uuid)
-                .await
-                .with_context(|| format!("could not mark task {} as done", task.id))?;
-        }
+                if !task.annotations.is_empty() {
+                    lines.push(Line::from(""));
 
-        Ok(())
-    }
+                    let mut link_index = 0;
 
-    pub fn extend(&mut self) {
-        match self {
-            Self::Task {
-                length,
-                original_length,
-                ..
-            } => {
-                *length += *original_length;
+                    for annotation in &task.annotations {
+                        let mut spans = vec![Span::from(format!(
+                            "{}  ",
+                            self.format_datetime(annotation.entry)
+                        ))
+                        .dim()];
+
+                        for (i, word) in annotation.description.split(' ').enumerate() {
+                            if i > 0 {
+                                spans.push(Span::from(" "));
+                            }
+
+                            if is_link(word) {
+                                let span = Span::from(word).underlined();
+
+                                spans.push(if link_index == *selected {
+                                    span.reversed()
+                                } else {
+                                    span
+                                });
+                                link_index += 1;
+                            } else {
+                                spans.push(Span::from(word));
+                            }
+                        }
+
+                        lines.push(Line::from(spans).left_aligned());
+                    }
+
+                    if link_index > 0 {
+                        lines.push(Line::from(""));
+                        lines.push(
+                            Line::from(format!(
+                                "{} to choose a link, enter to open it",
+                                self.glyph("↑/↓", "up/down")
+                            ))
+                            .dim(),
+                        );
+                    }
+                }
+
+                (" Task ", Text::from(lines))
             }
-            Self::Nothing => {}
-        }
-    }
+            Overlay::Stats => {
+                let tasks = self.pool.as_ref().map_or(&[][..], |pool| &pool.tasks[..]);
 
-    pub async fn refresh_task(&mut self, tw: &Taskwarrior) -> Result<()> {
-        if let Self::Task { task, .. } = self {
-            *task = tw
-                .export()
-                .with_filter(&task.uuid)
-                .with_filter("limit:1")
-                .call()
-                .await
-                .context("could not refresh task")?
-                .pop()
-                .context("could not find task")?;
-        }
+                let estimate = |task: &Task| task.estimate(&self.settings.estimate_uda);
 
-        Ok(())
-    }
-}
+                let (estimated, unestimated): (Vec<_>, Vec<_>) =
+                    tasks.iter().partition(|task| estimate(task).is_some());
 
-fn gauge_style(completed_time: bool) -> Style {
-    if completed_time {
-        Style::new()
-            .fg(tailwind::GREEN.c800)
-            .bg(tailwind::GREEN.c400)
-    } else {
-        Style::new().fg(tailwind::BLUE.c800).bg(tailwind::BLUE.c400)
-    }
-}
+                let total_estimate = estimated
+                    .iter()
+                    .filter_map(|task| estimate(task))
+                    .fold(Duration::zero(), |total, estimate| total + estimate);
 
-fn format_remaining(remaining: Duration) -> String {
-    format!(
-        "{}{}:{:02}",
-        if remaining < Duration::zero() {
-            "-"
+                let now = Utc::now();
+                let done = self
+                    .history
+                    .iter()
+                    .filter(|outcome| **outcome == Outcome::Done)
+                    .count();
+
+                let eta = match pool_eta(now - self.session_started, done, tasks.len()) {
+                    Some(eta) => Span::from(format!(
+                        "{} at this pace (around {})",
+                        format_hours_minutes(eta),
+                        (now + eta)
+                            .with_timezone(&Local)
+                            .format(self.settings.clock.time_format())
+                    )),
+                    None => Span::from("finish a task this session to see").dim(),
+                };
+
+                (
+                    " Stats ",
+                    Text::from(vec![
+                        Line::from(vec![
+                            Span::from("Ready: ").bold(),
+                            Span::from(format!("{} tasks", tasks.len())),
+                        ]),
+                        Line::from(vec![
+                            Span::from("Estimated work: ").bold(),
+                            Span::from(format_hours_minutes(total_estimate)),
+                            Span::from(format!(" ({} without an estimate)", unestimated.len()))
+                                .dim(),
+                        ]),
+                        Line::from(vec![Span::from("Clearing the pool: ").bold(), eta]),
+                    ]),
+                )
+            }
+        };
+
+        let height = body.height() as u16 + 2;
+
+        let [area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::horizontal([Constraint::Percentage(50)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let border = if self.ascii {
+            ASCII_BORDER
         } else {
-            ""
-        },
-        remaining.abs().num_minutes(),
-        remaining.abs().num_seconds() % 60
-    )
+            border::PLAIN
+        };
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(body)
+                .centered()
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().border_set(border).title(title)),
+            area,
+        );
+    }
+
+    /// Format an absolute date and time in the local timezone according to
+    /// the configured date format and clock.
+    fn format_datetime(&self, datetime: DateTime<Utc>) -> String {
+        datetime
+            .with_timezone(&Local)
+            .format(&format!(
+                "{} {}",
+                self.settings.date_format,
+                self.settings.clock.time_format()
+            ))
+            .to_string()
+    }
+
+    pub async fn handle_input(&mut self, event: Event) -> Result<()> {
+        match event {
+            Event::Key(key_event) => self.handle_key(key_event).await,
+            Event::Mouse(mouse_event) => self.handle_mouse(mouse_event).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Clicking on an action in the status line does the same thing as
+    /// pressing its key.
+    async fn handle_mouse(&mut self, mouse_event: MouseEvent) -> Result<()> {
+        if mouse_event.kind != MouseEventKind::Down(MouseButton::Left) || self.overlay.is_some() {
+            return Ok(());
+        }
+
+        let area = self.status_line_area.get();
+        if mouse_event.row != area.y {
+            return Ok(());
+        }
+
+        let status_line = self.status_line();
+
+        // This needs to match how ratatui lays out a centered line.
+        let line_width: usize = status_line.iter().map(|(span, _)| span.width()).sum();
+        let mut x = usize::from(area.x) + usize::from(area.width).saturating_sub(line_width) / 2;
+
+        let mut clicked = None;
+        for (span, code) in status_line {
+            let width = span.width();
+
+            if (x..x + width).contains(&usize::from(mouse_event.column)) {
+                clicked = code;
+                break;
+            }
+
+            x += width;
+        }
+
+        match clicked {
+            Some(key_event) => self.handle_key(key_event).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn handle_key(&mut self, key_event: KeyEvent) -> Result<()> {
+        self.message = None;
+
+        if let Some(overlay) = self.overlay.take() {
+            return self.handle_overlay_input(overlay, key_event.code).await;
+        }
+
+        match key_event.code {
+            KeyCode::Char('?') => {
+                self.overlay = Some(Overlay::Help);
+            }
+            KeyCode::Char('s') => {
+                // The pool may have been invalidated (e.g. by `a` or `P`), and
+                // these stats are all about it.
+                self.available_tasks().await?;
+
+                self.overlay = Some(Overlay::Stats);
+            }
+            KeyCode::Char('i') if !self.doing.is_nothing() => {
+                self.overlay = Some(Overlay::Details { selected: 0 });
+            }
+            KeyCode::Char('t') if !self.doing.is_nothing() => {
+                self.overlay = Some(Overlay::Prompt {
+                    prompt: Prompt::Tag,
+                    input: String::new(),
+                });
+            }
+            KeyCode::Char('+') => {
+                self.bump_priority(true).await?;
+            }
+            KeyCode::Char('-') => {
+                self.bump_priority(false).await?;
+            }
+            KeyCode::Char('l') => {
+                self.overlay = Some(Overlay::Prompt {
+                    prompt: Prompt::Length,
+                    input: String::new(),
+                });
+            }
+            KeyCode::Char('u') => {
+                self.overlay = Some(Overlay::Prompt {
+                    prompt: Prompt::Until,
+                    input: String::new(),
+                });
+            }
+            KeyCode::Char('/') => {
+                self.overlay = Some(Overlay::Prompt {
+                    prompt: Prompt::Search,
+                    input: String::new(),
+                });
+            }
+            KeyCode::Char('q') => {
+                self.should_quit = true;
+            }
+            KeyCode::Char(key @ ('d' | 'D')) => {
+                let stay = key == 'D';
+
+                if self.settings.confirm_done && !self.doing.is_nothing() {
+                    self.overlay = Some(Overlay::ConfirmDone { stay });
+                } else {
+                    self.ask_for_note_or_complete(stay).await?;
+                }
+            }
+            KeyCode::Char('r') => {
+                let now = Utc::now();
+
+                if self
+                    .last_reroll
+                    .is_some_and(|last| now - last < self.settings.reroll_cooldown)
+                {
+                    self.message = Some("reroll ignored, too soon after the last one".to_owned());
+                    return Ok(());
+                }
+
+                self.last_reroll = Some(now);
+
+                // If we came up empty, something may have changed since the
+                // last export, so look again instead of reusing the cache.
+                if self.doing.is_nothing() {
+                    self.invalidate_pool();
+                }
+
+                // Moving on after D is the next step, not a reroll.
+                if !self.holding {
+                    self.streak = 0;
+                }
+
+                self.doing = self.choose_next_task().await?;
+            }
+            KeyCode::Char('a') => {
+                self.stale = !self.stale;
+                self.invalidate_pool();
+
+                self.message = Some(if self.stale {
+                    "stale mode on: older tasks come up more often".to_owned()
+                } else {
+                    "stale mode off".to_owned()
+                });
+            }
+            KeyCode::Char('P') => {
+                let projects = self.ready_projects().await?;
+
+                self.overlay = Some(Overlay::Project {
+                    projects,
+                    input: String::new(),
+                    selected: 0,
+                });
+            }
+            KeyCode::Char('x') => {
+                if let Activity::Task { task, .. } = &self.doing {
+                    self.skipped.insert(task.uuid.clone(), Utc::now());
+                    self.message = Some(format!("skipped task {}", task.id));
+
+                    self.streak = 0;
+                    self.doing = self.choose_next_task().await?;
+                }
+            }
+            KeyCode::Char('p') => {
+                self.go_back().await?;
+            }
+            KeyCode::Char('m') => {
+                self.doing.extend(self.settings.extend_snap, Utc::now());
+            }
+            KeyCode::Char('e') => {
+                if let Activity::Task { task, .. } = &self.doing {
+                    let mut command = self.tw.command();
+                    command.arg(&task.uuid);
+                    command.arg("edit");
+
+                    self.interactive = Some((command, Refresh::Pool))
+                };
+            }
+            KeyCode::Char('w') => {
+                self.wait_doing("1h").await?;
+            }
+            KeyCode::Char('W') => {
+                let until = self.settings.defer.clone();
+
+                self.wait_doing(&until).await?;
+            }
+
+            // TODO: source these from config
+            KeyCode::Char('o') => {
+                if let Activity::Task { task, .. } = &self.doing {
+                    let mut command = Command::new("tw-open");
+                    command.arg(&task.uuid);
+                    command.envs(self.tw.env());
+                    command.envs(self.doing.env(Utc::now()));
+
+                    // Opening is a nice-to-have, so we report problems
+                    // instead of ending the session over them.
+                    self.message = match command.output().await {
+                        Ok(out) if out.status.success() => None,
+                        Ok(out) => Some(format!(
+                            "tw-open failed: {}",
+                            String::from_utf8_lossy(&out.stderr).trim()
+                        )),
+                        Err(err) => Some(format!("could not call tw-open: {}", err)),
+                    };
+                };
+            }
+            KeyCode::Char('b') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                let tasks = self.available_tasks().await?;
+
+                if tasks.is_empty() {
+                    self.message = Some("there are no ready tasks to break down".to_owned());
+                } else {
+                    let mut command = Command::new("tw-breakdown");
+                    command.arg("--seq");
+                    command.args(tasks.iter().map(|task| &task.uuid));
+
+                    self.interactive = Some((command, Refresh::Pool));
+                }
+            }
+            KeyCode::Char('b' | 'B') => {
+                if let Activity::Task { task, .. } = &self.doing {
+                    let mut command = Command::new("tw-breakdown");
+
+                    if !key_event.modifiers.contains(KeyModifiers::SHIFT) {
+                        command.arg("--seq");
+                    };
+
+                    command.arg(&task.uuid);
+
+                    // The task we broke down now waits on its new subtasks.
+                    self.interactive = Some((command, Refresh::Reroll))
+                };
+            }
+            KeyCode::Char('f') => {
+                if let Activity::Task {
+                    task,
+                    started,
+                    length,
+                    ..
+                } = &self.doing
+                {
+                    let remaining_seconds = (*length - (Utc::now() - started)).num_seconds();
+
+                    if remaining_seconds > 0 {
+                        if let Err(err) = open::that(format!(
+                            "raycast://focus/start?goal={}&categories={}&duration={}",
+                            urlencoding::encode(&task.description),
+                            self.settings.focus_categories.join(","),
+                            remaining_seconds,
+                        )) {
+                            self.message = Some(format!("could not start focus session: {}", err));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Handle a key press while an overlay is open. The overlay has already
+    /// been taken out of `self.overlay`, so it closes unless we put it back.
+    async fn handle_overlay_input(&mut self, overlay: Overlay, code: KeyCode) -> Result<()> {
+        match overlay {
+            Overlay::ConfirmDone { stay } => {
+                if matches!(code, KeyCode::Char('y') | KeyCode::Enter) {
+                    self.ask_for_note_or_complete(stay).await?;
+                }
+            }
+            Overlay::UpdateEstimate { uuid, actual, .. } => {
+                if matches!(code, KeyCode::Char('y') | KeyCode::Enter) {
+                    let minutes = actual.num_minutes().max(1);
+
+                    self.tw
+                        .modify()
+                        .with_subject(&uuid)
+                        .with_mod(&format!("{}:{}min", self.settings.estimate_uda, minutes))
+                        .call()
+                        .await
+                        .context("could not update the estimate")?;
+
+                    self.message = Some(format!(
+                        "set {} to {}",
+                        self.settings.estimate_uda,
+                        format_hours_minutes(Duration::minutes(minutes))
+                    ));
+                }
+            }
+            Overlay::Prompt { prompt, mut input } => match code {
+                KeyCode::Enter => self.submit_prompt(prompt, input.trim()).await?,
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    input.pop();
+                    self.overlay = Some(Overlay::Prompt { prompt, input });
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    self.overlay = Some(Overlay::Prompt { prompt, input });
+                }
+                _ => self.overlay = Some(Overlay::Prompt { prompt, input }),
+            },
+            Overlay::Project {
+                projects,
+                mut input,
+                mut selected,
+            } => {
+                let matches = project_matches(&projects, &input);
+
+                match code {
+                    KeyCode::Enter => {
+                        let Some(choice) = matches.get(selected) else {
+                            return Ok(());
+                        };
+
+                        self.project = choice.map(str::to_owned);
+                        self.invalidate_pool();
+
+                        self.message = Some(match &self.project {
+                            Some(project) => format!("only picking from project {}", project),
+                            None => "picking from every project".to_owned(),
+                        });
+
+                        return Ok(());
+                    }
+                    KeyCode::Esc => return Ok(()),
+                    KeyCode::Up => selected = selected.saturating_sub(1),
+                    KeyCode::Down => selected = (selected + 1).min(matches.len().saturating_sub(1)),
+                    KeyCode::Backspace => {
+                        input.pop();
+                        selected = 0;
+                    }
+                    KeyCode::Char(c) => {
+                        input.push(c);
+                        selected = 0;
+                    }
+                    _ => {}
+                }
+
+                self.overlay = Some(Overlay::Project {
+                    projects,
+                    input,
+                    selected,
+                });
+            }
+            Overlay::Help => {
+                if !matches!(code, KeyCode::Char('?') | KeyCode::Esc) {
+                    self.overlay = Some(overlay);
+                }
+            }
+            Overlay::Pick {
+                mut tasks,
+                mut selected,
+            } => match code {
+                KeyCode::Enter => {
+                    let task = tasks.swap_remove(selected);
+
+                    self.streak = 0;
+                    self.doing = self.start_task(task, Utc::now());
+                }
+                KeyCode::Esc => {}
+                KeyCode::Up | KeyCode::Char('k') => {
+                    selected = selected.saturating_sub(1);
+                    self.overlay = Some(Overlay::Pick { tasks, selected });
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    selected = (selected + 1).min(tasks.len() - 1);
+                    self.overlay = Some(Overlay::Pick { tasks, selected });
+                }
+                _ => self.overlay = Some(Overlay::Pick { tasks, selected }),
+            },
+            Overlay::Details { mut selected } => {
+                let links = match &self.doing {
+                    Activity::Task { task, .. } => links(task),
+                    Activity::Nothing => Vec::new(),
+                };
+
+                match code {
+                    KeyCode::Char('i') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Up | KeyCode::Char('k') => selected = selected.saturating_sub(1),
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        selected = (selected + 1).min(links.len().saturating_sub(1))
+                    }
+                    KeyCode::Enter => {
+                        if let Some(link) = links.get(selected) {
+                            if let Err(err) = open::that(link) {
+                                self.message = Some(format!("could not open {}: {}", link, err));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+
+                self.overlay = Some(Overlay::Details { selected });
+            }
+            Overlay::Stats => {
+                if !matches!(code, KeyCode::Char('s') | KeyCode::Esc) {
+                    self.overlay = Some(overlay);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Go back to the task we were on before this one with a fresh timer.
+    async fn go_back(&mut self) -> Result<()> {
+        let Some(uuid) = self.previous.clone() else {
+            self.message = Some("no previous task this session".to_owned());
+            return Ok(());
+        };
+
+        let task = self
+            .tw
+            .export()
+            .with_filter(&uuid)
+            .with_filter("status:pending")
+            .with_filter("limit:1")
+            .call()
+            .await
+            .context("could not get the previous task")?
+            .pop();
+
+        match task {
+            Some(task) => {
+                self.streak = 0;
+                self.doing = self.start_task(task, Utc::now());
+            }
+            None => {
+                self.message = Some("the previous task is no longer pending".to_owned());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hide the current task until `until` (any Taskwarrior date expression)
+    /// and move on to something else.
+    async fn wait_doing(&mut self, until: &str) -> Result<()> {
+        let Activity::Task { task, .. } = &self.doing else {
+            return Ok(());
+        };
+
+        let uuid = task.uuid.clone();
+        let id = task.id;
+
+        self.tw
+            .modify()
+            .with_subject(&uuid)
+            .with_mod(&format!("wait:{}", until))
+            .call()
+            .await
+            .with_context(|| format!("could not modify task {}", id))?;
+
+        let waited = self
+            .tw
+            .export()
+            .with_filter(&uuid)
+            .with_filter("limit:1")
+            .call()
+            .await
+            .context("could not look up the new wait date")?
+            .pop();
+
+        self.message = match waited.and_then(|task| task.wait) {
+            Some(wait) => Some(format!(
+                "{} is waiting until {}",
+                id,
+                self.format_datetime(wait)
+            )),
+            None => Some(format!("{} is waiting until {}", id, until)),
+        };
+
+        self.invalidate_pool();
+        self.streak = 0;
+        self.doing = self.choose_next_task().await?;
+
+        Ok(())
+    }
+
+    async fn submit_prompt(&mut self, prompt: Prompt, input: &str) -> Result<()> {
+        match prompt {
+            Prompt::DoneNote { stay } => {
+                let note = Some(input).filter(|note| !note.is_empty());
+
+                self.complete_doing(note, stay).await?;
+            }
+            Prompt::Length => {
+                if input.is_empty() {
+                    return Ok(());
+                }
+
+                let length = match crate::dates::parse_duration(input) {
+                    Ok(length) if length > MAX_LENGTH => {
+                        self.message = Some(format!(
+                            "the timer can't be longer than {}",
+                            format_hours_minutes(MAX_LENGTH)
+                        ));
+                        return Ok(());
+                    }
+                    Ok(length) if length > Duration::zero() => length,
+                    Ok(_) => {
+                        self.message = Some("the timer needs to be longer than that".to_owned());
+                        return Ok(());
+                    }
+                    Err(err) => {
+                        self.message = Some(format!("{}", err));
+                        return Ok(());
+                    }
+                };
+
+                if self.doing.is_nothing() {
+                    self.doing = self.choose_next_task().await?;
+                }
+
+                self.doing.restart(length, Utc::now());
+            }
+            Prompt::Until => {
+                if input.is_empty() {
+                    return Ok(());
+                }
+
+                let now = Utc::now();
+                let end = match next_time_of_day(input, now, &Local) {
+                    Ok(end) => end,
+                    Err(err) => {
+                        self.message = Some(format!("{}", err));
+                        return Ok(());
+                    }
+                };
+
+                if self.doing.is_nothing() {
+                    self.doing = self.choose_next_task().await?;
+                }
+
+                self.doing.restart(end - now, now);
+            }
+            Prompt::Search => {
+                if input.is_empty() {
+                    return Ok(());
+                }
+
+                let tasks = self
+                    .tw
+                    .export()
+                    .with_filter("status:pending")
+                    .with_filter(&format!("description.contains:{}", input))
+                    .call()
+                    .await
+                    .context("could not search tasks")?;
+
+                if tasks.is_empty() {
+                    self.message = Some(format!("no pending tasks match \"{}\"", input));
+                } else {
+                    self.overlay = Some(Overlay::Pick { tasks, selected: 0 });
+                }
+            }
+            Prompt::Tag => {
+                let Activity::Task { task, .. } = &self.doing else {
+                    return Ok(());
+                };
+
+                let tags = input
+                    .split_whitespace()
+                    .map(|tag| {
+                        if tag.starts_with('+') || tag.starts_with('-') {
+                            tag.to_owned()
+                        } else {
+                            format!("+{}", tag)
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                if tags.is_empty() {
+                    return Ok(());
+                }
+
+                let mut modify = self.tw.modify().with_subject(&task.uuid);
+                for tag in &tags {
+                    modify = modify.with_mod(tag);
+                }
+
+                modify
+                    .call()
+                    .await
+                    .with_context(|| format!("could not tag task {}", task.id))?;
+
+                self.message = Some(format!("tagged {}", tags.join(" ")));
+                self.refresh_doing().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Raise or lower the current task's priority by one step.
+    async fn bump_priority(&mut self, raise: bool) -> Result<()> {
+        let Activity::Task { task, .. } = &self.doing else {
+            return Ok(());
+        };
+
+        let priority = step_priority(task.priority.as_deref(), raise);
+
+        self.tw
+            .modify()
+            .with_subject(&task.uuid)
+            .with_mod(&format!("priority:{}", priority))
+            .call()
+            .await
+            .with_context(|| format!("could not change the priority of task {}", task.id))?;
+
+        self.message = Some(if priority.is_empty() {
+            "removed priority".to_owned()
+        } else {
+            format!("priority is now {}", priority)
+        });
+
+        self.refresh_doing().await
+    }
+
+    async fn ask_for_note_or_complete(&mut self, stay: bool) -> Result<()> {
+        if self.settings.done_note && !self.doing.is_nothing() {
+            self.overlay = Some(Overlay::Prompt {
+                prompt: Prompt::DoneNote { stay },
+                input: String::new(),
+            });
+
+            Ok(())
+        } else {
+            self.complete_doing(None, stay).await
+        }
+    }
+
+    /// Mark the current task done, then pick the next one unless `stay` is
+    /// set, in which case we wait on the nothing screen for `r`.
+    async fn complete_doing(&mut self, note: Option<&str>, stay: bool) -> Result<()> {
+        if let (Some(note), Activity::Task { task, .. }) = (note, &self.doing) {
+            self.tw
+                .annotate(&task.uuid, note)
+                .await
+                .with_context(|| format!("could not annotate task {}", task.id))?;
+        }
+
+        if let Activity::Task { task, started, .. } = &self.doing {
+            if self.settings.time_note {
+                let (earlier, visits) = self
+                    .time_spent
+                    .get(&task.uuid)
+                    .copied()
+                    .unwrap_or((Duration::zero(), 0));
+
+                self.tw
+                    .annotate(
+                        &task.uuid,
+                        &time_note(earlier + (Utc::now() - *started), visits + 1),
+                    )
+                    .await
+                    .with_context(|| format!("could not annotate task {}", task.id))?;
+            }
+        }
+
+        self.doing.mark_done(&self.tw).await?;
+        self.invalidate_pool();
+
+        if let (
+            Some(database),
+            Activity::Task {
+                task,
+                started,
+                original_length,
+                ..
+            },
+        ) = (&self.database, &self.doing)
+        {
+            let now = Utc::now();
+
+            database.record_completion(Completion {
+                uuid: task.uuid.clone(),
+                description: task.description.clone(),
+                project: task.project.clone(),
+                planned: *original_length,
+                actual: now - *started,
+                completed_at: now,
+            });
+        }
+
+        if !self.doing.is_nothing() {
+            self.streak += 1;
+        }
+
+        let update_estimate = match &self.doing {
+            Activity::Task {
+                task,
+                started,
+                original_length,
+                ..
+            } if self.settings.estimate_prompt => {
+                let actual = Utc::now() - *started;
+
+                estimate_is_off(
+                    *original_length,
+                    actual,
+                    self.settings.estimate_prompt_threshold,
+                )
+                .then(|| Overlay::UpdateEstimate {
+                    uuid: task.uuid.clone(),
+                    description: task.description.clone(),
+                    actual,
+                })
+            }
+            _ => None,
+        };
+
+        if !self.doing.is_nothing() {
+            self.history.push(Outcome::Done);
+        }
+
+        // We're done with this task, so it shouldn't count as the previous
+        // one or as time spent on a task we left.
+        self.doing = Activity::Nothing;
+        self.holding = stay;
+        if !stay {
+            self.doing = self.choose_next_task().await?;
+        }
+        self.overlay = update_estimate;
+
+        Ok(())
+    }
+
+    pub async fn handle_tick(&mut self) -> Result<()> {
+        if let Some(asleep) = self.slept(Utc::now()) {
+            match self.settings.after_sleep {
+                AfterSleep::Ignore => {}
+                AfterSleep::Pause => self.doing.pause_for(asleep),
+                AfterSleep::Reroll => {
+                    if !self.doing.is_nothing() {
+                        self.streak = 0;
+                        self.doing = self
+                            .choose_next_task()
+                            .await
+                            .context("could not move on after sleeping")?;
+                    }
+                }
+            }
+        }
+
+        if self.doing.is_nothing() {
+            if self.holding {
+                return Ok(());
+            }
+
+            self.doing = self
+                .choose_next_task()
+                .await
+                .context("could not set a task")?;
+
+            if self.doing.is_nothing() && self.settings.explain_nothing {
+                self.explain_nothing().await;
+            }
+        } else if self.overlay.is_none() && self.doing.is_expired(Utc::now()) {
+            match self.settings.auto_advance {
+                AutoAdvance::Off => {}
+                AutoAdvance::Reroll => {
+                    self.streak = 0;
+                    self.doing = self
+                        .choose_next_task()
+                        .await
+                        .context("could not move on to the next task")?;
+                }
+                AutoAdvance::Done => {
+                    self.complete_doing(None, false)
+                        .await
+                        .context("could not complete the expired task")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Count the tasks that would be ready but for waiting, being blocked, or
+    /// being scheduled later today. Failing to get these shouldn't stop us,
+    /// so we just log it.
+    async fn explain_nothing(&mut self) {
+        let now = Utc::now();
+
+        if self
+            .not_ready
+            .as_ref()
+            .is_some_and(|not_ready| now - not_ready.checked < self.settings.cache_ttl)
+        {
+            return;
+        }
+
+        let waiting = self.count_tasks(&["+WAITING"]).await;
+        let blocked = self.count_tasks(&["+BLOCKED"]).await;
+        let scheduled = self
+            .count_tasks(&["scheduled.after:now", "scheduled.before:tomorrow"])
+            .await;
+
+        match (waiting, blocked, scheduled) {
+            (Ok(waiting), Ok(blocked), Ok(scheduled)) => {
+                self.not_ready = Some(NotReady {
+                    checked: now,
+                    waiting,
+                    blocked,
+                    scheduled,
+                });
+            }
+            (waiting, blocked, scheduled) => {
+                tracing::warn!(
+                    ?waiting,
+                    ?blocked,
+                    ?scheduled,
+                    "could not explain the empty pool"
+                );
+                self.not_ready = None;
+            }
+        }
+    }
+
+    /// How many tasks match `filters` on top of the ones the user chose.
+    async fn count_tasks(&self, filters: &[&str]) -> Result<usize> {
+        let mut export = self.tw.export();
+
+        for tag in &self.settings.include_tags {
+            export = export.with_filter(&format!("+{}", tag));
+        }
+
+        for filter in &self.filters {
+            export = export.with_filter(filter);
+        }
+
+        if let Some(project) = &self.project {
+            export = export.with_filter(&format!("project:{}", project));
+        }
+
+        for filter in filters {
+            export = export.with_filter(filter);
+        }
+
+        Ok(export.call().await?.len())
+    }
+
+    /// How long we were asleep, if the gap since the last tick is too long
+    /// to be anything else.
+    fn slept(&mut self, now: DateTime<Utc>) -> Option<Duration> {
+        let gap = self.last_tick.map(|last| now - last);
+        self.last_tick = Some(now);
+
+        let threshold = (self.poll_interval * SLEEP_GAP_POLLS).max(MIN_SLEEP_GAP);
+
+        gap.filter(|gap| *gap > threshold)
+    }
+
+    async fn available_tasks(&mut self) -> Result<Vec<Task>> {
+        let now = Utc::now();
+
+        if let Some(pool) = &self.pool {
+            if now - pool.fetched < self.settings.cache_ttl {
+                return Ok(pool.tasks.clone());
+            }
+        }
+
+        let mut export = self.tw.export();
+        for (key, coefficient) in &self.settings.urgency_coefficients {
+            export = export.with_urgency_coefficient(key, *coefficient);
+        }
+
+        if self.stale {
+            export = export.with_urgency_coefficient("age", STALE_AGE_COEFFICIENT);
+        }
+
+        for filter in self.pool_filters() {
+            export = export.with_filter(&filter);
+        }
+
+        let mut tasks = export.call().await.context("could not get tasks")?;
+
+        tasks.retain(|task| !task.is_recurrence_template());
+
+        // `-BLOCKED` should already have done this, but custom readiness
+        // rules can let blocked tasks through. Anything a task depends on
+        // that showed up in the same export is certainly still pending.
+        if self.settings.skip_blocked {
+            let pending: HashSet<String> = tasks.iter().map(|task| task.uuid.clone()).collect();
+            tasks.retain(|task| !task.is_blocked_by(&pending));
+        }
+
+        if self.settings.hide_scheduled {
+            tasks.retain(|task| task.scheduled.is_none_or(|scheduled| scheduled <= now));
+        }
+
+        if let Some(uda) = &self.settings.not_before_uda {
+            tasks.retain(|task| {
+                task.datetime_uda(uda)
+                    .is_none_or(|not_before| not_before <= now)
+            });
+        }
+
+        self.pool = Some(Pool {
+            fetched: now,
+            tasks: tasks.clone(),
+        });
+
+        Ok(tasks)
+    }
+
+    /// The Taskwarrior filters that decide which tasks we pick from.
+    fn pool_filters(&self) -> Vec<String> {
+        let mut filters = self.ready_filters();
+
+        // Taskwarrior matches `project:work` against `work.backend` too.
+        if let Some(project) = &self.project {
+            filters.push(format!("project:{}", project));
+        }
+
+        filters
+    }
+
+    /// Like `pool_filters`, but without narrowing to `self.project`.
+    fn ready_filters(&self) -> Vec<String> {
+        let mut filters = self
+            .settings
+            .include_tags
+            .iter()
+            .map(|tag| format!("+{}", tag))
+            .collect_vec();
+
+        filters.extend([
+            "jirastatus.not:backlog".to_owned(),
+            "+READY".to_owned(),
+            "status.not:recurring".to_owned(),
+        ]);
+
+        if self.settings.skip_blocked {
+            filters.push("-BLOCKED".to_owned());
+        }
+
+        filters.extend(self.filters.iter().cloned());
+
+        filters
+    }
+
+    /// Every project with a ready task, along with the parents of nested
+    /// projects so we can choose a whole tree at once.
+    async fn ready_projects(&self) -> Result<Vec<String>> {
+        let mut export = self.tw.export();
+        for filter in self.ready_filters() {
+            export = export.with_filter(&filter);
+        }
+
+        let tasks = export.call().await.context("could not get projects")?;
+
+        Ok(tasks
+            .iter()
+            .filter_map(|task| task.project.as_deref())
+            .flat_map(|project| {
+                project
+                    .match_indices('.')
+                    .map(|(end, _)| &project[..end])
+                    .chain([project])
+            })
+            .map(str::to_owned)
+            .sorted()
+            .dedup()
+            .collect())
+    }
+
+    /// Forget the cached pool of available tasks. Call this after changing
+    /// anything in Taskwarrior so we don't pick a stale task.
+    pub fn invalidate_pool(&mut self) {
+        self.last_ready_count = self.ready_count();
+        self.pool = None;
+    }
+
+    /// Each ready task's urgency under `settings.preview_urgency_coefficients`
+    /// instead of the coefficients we pick with, keyed by uuid.
+    pub async fn preview_urgency(&self) -> Result<HashMap<String, f64>> {
+        let mut export = self.tw.export();
+        for (key, coefficient) in &self.settings.preview_urgency_coefficients {
+            export = export.with_urgency_coefficient(key, *coefficient);
+        }
+
+        for filter in self.pool_filters() {
+            export = export.with_filter(&filter);
+        }
+
+        Ok(export
+            .call()
+            .await
+            .context("could not get tasks to preview")?
+            .into_iter()
+            .map(|task| (task.uuid, task.urgency))
+            .collect())
+    }
+
+    /// Every task we could pick right now, along with its weight.
+    pub async fn candidates(&mut self, now: DateTime<Utc>) -> Result<Vec<(Task, f64)>> {
+        let tasks = self.available_tasks().await?;
+
+        Ok(tasks
+            .into_iter()
+            .map(|task| {
+                let weight = self.weight(&task, now);
+                (task, weight)
+            })
+            .collect())
+    }
+
+    fn weight(&self, task: &Task, now: DateTime<Utc>) -> f64 {
+        let context = WeightContext {
+            now,
+            config: &self.config,
+            settings: &self.settings,
+            recently_shown: &self.recently_shown,
+            skipped: &self.skipped,
+            last_project: self.last_project.as_deref(),
+        };
+
+        let weight: f64 = crate::weight::pipeline(&self.settings)
+            .iter()
+            .map(|weight| weight.strategy().multiplier(task, &context))
+            .product();
+
+        // choose_weighted fails outright on a single negative or non-finite
+        // weight (e.g. from a negative due_boost_factor), so those tasks just
+        // can't be picked.
+        if weight.is_finite() {
+            weight.max(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Pick exactly once and keep the result, even if nothing is ready.
+    /// Unlike `handle_tick`, this never tries again when it comes up empty,
+    /// so it's what to use outside the UI loop.
+    pub async fn pick_once(&mut self) -> Result<&Activity> {
+        self.doing = self.choose_next_task().await?;
+
+        Ok(&self.doing)
+    }
+
+    /// Pick up to `count` tasks to do back to back starting now, without
+    /// picking any task twice. This stops early if we run out of tasks.
+    pub async fn plan(&mut self, count: usize) -> Result<Vec<Activity>> {
+        let mut at = Utc::now();
+        let mut plan = Vec::new();
+        let mut picked = HashSet::new();
+
+        for _ in 0..count {
+            let now = Utc::now();
+            let mut candidates = self.candidates(now).await?;
+            candidates.retain(|(task, _)| !picked.contains(&task.uuid));
+
+            let mut activity = self.choose_from(candidates, now)?;
+
+            let Activity::Task { task, started, .. } = &mut activity else {
+                break;
+            };
+
+            picked.insert(task.uuid.clone());
+
+            *started = at;
+            let next = activity.ends_at();
+            plan.push(activity);
+
+            // There's no planning past a timer that never runs out.
+            match next {
+                Some(next) => at = next,
+                None => break,
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// One line per planned activity with when it starts and ends.
+    pub fn agenda(&self, plan: &[Activity]) -> Vec<String> {
+        let time = |datetime: DateTime<Utc>| {
+            datetime
+                .with_timezone(&Local)
+                .format(self.settings.clock.time_format())
+                .to_string()
+        };
+
+        plan.iter()
+            .filter_map(|activity| {
+                let Activity::Task { started, .. } = activity else {
+                    return None;
+                };
+
+                Some(format!(
+                    "{:>8} - {:<8}  {}",
+                    time(*started),
+                    time(activity.ends_at()?),
+                    activity.summary()
+                ))
+            })
+            .collect()
+    }
+
+    pub async fn choose_next_task(&mut self) -> Result<Activity> {
+        let now = Utc::now();
+
+        let candidates = self.candidates(now).await?;
+
+        self.choose_from(candidates, now)
+    }
+
+    /// Pick one of `candidates` by weight and start it.
+    fn choose_from(
+        &mut self,
+        candidates: Vec<(Task, f64)>,
+        now: DateTime<Utc>,
+    ) -> Result<Activity> {
+        if candidates.is_empty() {
+            return Ok(Activity::Nothing);
+        }
+
+        // choose_weighted needs at least one positive weight, which aggressive
+        // urgency coefficients can take away. Better to pick something than
+        // to stop working entirely.
+        let (task, _) = if candidates.iter().all(|(_, weight)| *weight <= 0.0) {
+            self.message =
+                Some("every task has zero weight, so this one was picked at random".to_owned());
+
+            candidates
+                .choose(&mut self.rng)
+                .context("could not choose a task")?
+        } else {
+            candidates
+                .choose_weighted(&mut self.rng, |(_, weight)| *weight)
+                .context("could not choose a task")?
+        };
+
+        Ok(self.start_task(task.clone(), now))
+    }
+
+    /// Start working on a task, with the timer set from its estimate.
+    fn start_task(&mut self, task: Task, now: DateTime<Utc>) -> Activity {
+        let target_duration = Duration::minutes(25);
+
+        self.holding = false;
+
+        if let Activity::Task {
+            task: current,
+            started,
+            ..
+        } = &self.doing
+        {
+            if current.uuid != task.uuid {
+                self.previous = Some(current.uuid.clone());
+                self.history.push(Outcome::Left);
+            }
+
+            let spent = self
+                .time_spent
+                .entry(current.uuid.clone())
+                .or_insert((Duration::zero(), 0));
+            spent.0 += now - *started;
+            spent.1 += 1;
+        }
+
+        self.recently_shown.insert(task.uuid.clone(), now);
+        self.last_project = task.project.clone();
+
+        let mut length = task
+            .estimate(&self.settings.estimate_uda)
+            .unwrap_or(target_duration)
+            .min(target_duration);
+
+        if let Some(max) = self.settings.length_max {
+            length = length.min(max);
+        }
+        if let Some(min) = self.settings.length_min {
+            length = length.max(min);
+        }
+
+        Activity::Task {
+            task,
+            started: now,
+            length,
+            original_length: length,
+            extensions: 0,
+            overridden: false,
+        }
+    }
+
+    /// Make Timewarrior track whatever we're doing now: start a new interval
+    /// when the task changes and stop when there's nothing to do or we're
+    /// quitting. Failures are shown as a message rather than interrupting
+    /// work.
+    pub async fn sync_time_log(&mut self) {
+        let Some(timewarrior) = &self.timewarrior else {
+            return;
+        };
+
+        let current = match &self.doing {
+            Activity::Task { task, .. } if !self.should_quit => Some(task),
+            _ => None,
+        };
+
+        let current_uuid = current.map(|task| task.uuid.clone());
+        if current_uuid == self.tracking {
+            return;
+        }
+
+        let result = match current {
+            Some(task) => {
+                let mut tags = vec![task.description.clone()];
+                tags.extend(task.tags.iter().sorted().cloned());
+
+                timewarrior.start(&tags).await
+            }
+            None => timewarrior.stop().await,
+        };
+
+        // Even if this failed, don't try again every tick. We'll try again
+        // when the task changes.
+        self.tracking = current_uuid;
+
+        if let Err(err) = result {
+            tracing::warn!(?err, "could not update timewarrior");
+            self.message = Some(format!("could not update timewarrior: {}", err));
+        }
+    }
+
+    /// Count how many pending tasks are waiting on the current task, for
+    /// showing "blocks N". This is another export, so we only do it when the
+    /// task changes, and failures just leave the count as it was.
+    pub async fn sync_blocking(&mut self) {
+        let Activity::Task { task, .. } = &mut self.doing else {
+            return;
+        };
+
+        if let Some((uuid, blocking)) = &self.blocking_counted {
+            if *uuid == task.uuid {
+                task.blocking = *blocking;
+                return;
+            }
+        }
+
+        let uuid = task.uuid.clone();
+        let previous = task.blocking;
+
+        let blocking = match self
+            .tw
+            .export()
+            .with_filter(&format!("depends.has:{}", uuid))
+            .with_filter("status:pending")
+            .call()
+            .await
+        {
+            Ok(dependents) => dependents.len(),
+            Err(err) => {
+                tracing::warn!(?err, "could not count blocked tasks");
+                previous
+            }
+        };
+
+        // Even if that failed, don't try again every tick.
+        self.blocking_counted = Some((uuid, blocking));
+
+        if let Activity::Task { task, .. } = &mut self.doing {
+            task.blocking = blocking;
+        }
+    }
+
+    /// Stop tracking time, however we're leaving.
+    pub async fn stop_time_log(&mut self) {
+        self.should_quit = true;
+        self.sync_time_log().await;
+    }
+
+    fn ready_count(&self) -> Option<usize> {
+        self.pool
+            .as_ref()
+            .map(|pool| pool.tasks.len())
+            .or(self.last_ready_count)
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// A notification to send if the timer has run out since we last sent
+    /// one, when `settings.terminal_notify` is on.
+    pub fn take_notification(&mut self, now: DateTime<Utc>) -> Option<String> {
+        if !self.settings.terminal_notify || !self.doing.is_expired(now) {
+            return None;
+        }
+
+        let ends = self.doing.ends_at();
+        if ends == self.notified {
+            return None;
+        }
+
+        self.notified = ends;
+        Some(format!("Time's up: {}", self.doing.summary()))
+    }
+
+    pub fn take_interactive(&mut self) -> Option<(Command, Refresh)> {
+        let (mut command, refresh) = self.interactive.take()?;
+        command.envs(self.tw.env());
+        command.envs(self.doing.env(Utc::now()));
+
+        Some((command, refresh))
+    }
+
+    /// Catch up with whatever an interactive command changed.
+    pub async fn refresh(&mut self, refresh: Refresh) -> Result<()> {
+        // We don't tick while an interactive command runs, so the gap since
+        // the last tick isn't sleep.
+        self.last_tick = None;
+
+        match refresh {
+            Refresh::Pool => self.refresh_doing().await,
+            Refresh::Reroll => {
+                self.invalidate_pool();
+                self.doing = self.choose_next_task().await?;
+
+                Ok(())
+            }
+        }
+    }
+
+    pub async fn refresh_doing(&mut self) -> Result<()> {
+        self.invalidate_pool();
+
+        // Editing can add or remove dependencies, so count again.
+        self.blocking_counted = None;
+
+        if let Some(id) = self.doing.refresh_task(&self.tw).await? {
+            self.message = Some(format!("task {} is no longer pending", id));
+        }
+
+        // Editing can also take a task out of the pool without finishing it
+        // (e.g. by setting a wait date), in which case we move on.
+        if let Activity::Task { task, .. } = &self.doing {
+            let uuid = task.uuid.clone();
+            let id = task.id;
+
+            let pool = self.available_tasks().await?;
+            if !pool.iter().any(|task| task.uuid == uuid) {
+                self.message = Some(format!("task {} is no longer ready", id));
+                self.doing = self.choose_next_task().await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum Overlay {
+    /// `stay` is whether to wait instead of picking the next task after.
+    ConfirmDone {
+        stay: bool,
+    },
+    Help,
+    Stats,
+
+    /// Offer to update a task we just finished with how long it actually
+    /// took.
+    UpdateEstimate {
+        uuid: String,
+        description: String,
+        actual: Duration,
+    },
+
+    /// Everything about the current task. `selected` is the index of the
+    /// highlighted link across all the task's annotations.
+    Details {
+        selected: usize,
+    },
+    Prompt {
+        prompt: Prompt,
+        input: String,
+    },
+
+    /// Choose one of a list of tasks to work on right now.
+    Pick {
+        tasks: Vec<Task>,
+        selected: usize,
+    },
+
+    /// Choose a project to pick from, searching with `input`. `selected` is
+    /// the index into whatever matches `input`.
+    Project {
+        projects: Vec<String>,
+        input: String,
+        selected: usize,
+    },
+}
+
+/// What an interactive command may have changed, and so what we need to
+/// look at again once it's done.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Refresh {
+    /// Re-read the current task and the pool, moving on only if the current
+    /// task is no longer ready (e.g. after an edit.)
+    Pool,
+
+    /// Re-read the pool and pick something new, because the command was
+    /// about replacing the current task (e.g. breaking it down.)
+    Reroll,
+}
+
+/// How a task we had this session ended.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Outcome {
+    /// We marked it done.
+    Done,
+
+    /// We moved on to something else without finishing it.
+    Left,
+}
+
+/// How many outcomes to show in the session history.
+const HISTORY_LENGTH: usize = 20;
+
+/// Overlay borders for when we're sticking to ASCII.
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// What we're asking for when we prompt for text.
+#[derive(Debug)]
+enum Prompt {
+    /// An optional annotation to add before marking the task done. An empty
+    /// note skips the annotation.
+    DoneNote { stay: bool },
+
+    /// Tags to add (`+tag` or just `tag`) or remove (`-tag`) from the task.
+    Tag,
+
+    /// Text to look for in pending task descriptions.
+    Search,
+
+    /// A duration to restart the timer with, instead of the one we picked.
+    Length,
+
+    /// A time of day to restart the timer to end at.
+    Until,
+}
+
+/// Besides keys, these can go in the status line.
+const STATUS_LINE_EXTRAS: &[&str] = &["stale", "streak", "ready", "clock"];
+
+/// What's in the status line unless the config says otherwise: the main keys,
+/// then stale mode, the streak, and how many tasks are ready.
+fn default_status_line() -> Vec<String> {
+    KEYS.iter()
+        .filter(|binding| binding.in_status_line)
+        .map(|binding| binding.key)
+        .chain(["stale", "streak", "ready"])
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Make sure everything in a configured status line is something we know how
+/// to show.
+pub fn check_status_line(items: &[String]) -> Result<()> {
+    for item in items {
+        if !STATUS_LINE_EXTRAS.contains(&item.as_str())
+            && !KEYS.iter().any(|binding| binding.key == item)
+        {
+            return Err(anyhow::anyhow!(
+                "\"{}\" can't go in the status line. Use a key or one of: {}",
+                item,
+                STATUS_LINE_EXTRAS.join(", ")
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// A key the app responds to.
+struct Binding {
+    key: &'static str,
+
+    /// A terse label for the status line. If it starts with the key, the key
+    /// is highlighted in place (e.g. **d**one.)
+    label: &'static str,
+
+    /// A longer explanation for the help overlay.
+    description: &'static str,
+
+    in_status_line: bool,
+}
+
+impl Binding {
+    /// What pressing this key looks like, so clicking it in the status line
+    /// can do the same thing.
+    fn key_event(&self) -> Option<KeyEvent> {
+        let (key, modifiers) = match self.key.strip_prefix("Ctrl-") {
+            Some(key) => (key, KeyModifiers::CONTROL),
+            None => (self.key, KeyModifiers::NONE),
+        };
+
+        let code = match key {
+            "Esc" => KeyCode::Esc,
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(char), None) => KeyCode::Char(char),
+                    _ => return None,
+                }
+            }
+        };
+
+        Some(KeyEvent::new(code, modifiers))
+    }
+}
+
+/// Every key the app responds to. The status line and help overlay are both
+/// generated from this, so keep it in sync with `handle_input`.
+const KEYS: &[Binding] = &[
+    Binding {
+        key: "d",
+        label: "done",
+        description: "mark the task done and reroll",
+        in_status_line: true,
+    },
+    Binding {
+        key: "D",
+        label: "done, wait",
+        description: "mark the task done and wait for r before picking another",
+        in_status_line: false,
+    },
+    Binding {
+        key: "e",
+        label: "edit",
+        description: "edit the task in your editor",
+        in_status_line: true,
+    },
+    Binding {
+        key: "f",
+        label: "focus",
+        description: "start a Raycast focus session for the time remaining",
+        in_status_line: true,
+    },
+    Binding {
+        key: "m",
+        label: "more time",
+        description: "extend the timer by the original length (or to the next extend_snap)",
+        in_status_line: true,
+    },
+    Binding {
+        key: "r",
+        label: "reroll",
+        description: "pick a different task, or look for tasks again if there's nothing to do",
+        in_status_line: true,
+    },
+    Binding {
+        key: "a",
+        label: "stale mode",
+        description: "toggle stale mode, which favors the oldest tasks",
+        in_status_line: false,
+    },
+    Binding {
+        key: "x",
+        label: "skip",
+        description: "pick a different task and make this one less likely to come back soon",
+        in_status_line: false,
+    },
+    Binding {
+        key: "p",
+        label: "previous",
+        description: "go back to the task before this one",
+        in_status_line: false,
+    },
+    Binding {
+        key: "q",
+        label: "quit",
+        description: "quit task-rand",
+        in_status_line: true,
+    },
+    Binding {
+        key: "w",
+        label: "wait 1h",
+        description: "hide the task for an hour and reroll",
+        in_status_line: true,
+    },
+    Binding {
+        key: "W",
+        label: "defer",
+        description: "hide the task until defer (tomorrow by default) and reroll",
+        in_status_line: false,
+    },
+    Binding {
+        key: "o",
+        label: "open",
+        description: "open the task with tw-open",
+        in_status_line: true,
+    },
+    Binding {
+        key: "b",
+        label: "breakdown",
+        description: "break the task into sequential subtasks with tw-breakdown",
+        in_status_line: true,
+    },
+    Binding {
+        key: "B",
+        label: "breakdown",
+        description: "break the task into parallel subtasks with tw-breakdown",
+        in_status_line: false,
+    },
+    Binding {
+        key: "Ctrl-b",
+        label: "breakdown all",
+        description: "break down every ready task with tw-breakdown",
+        in_status_line: false,
+    },
+    Binding {
+        key: "t",
+        label: "tag",
+        description: "add (+tag) or remove (-tag) tags on the task",
+        in_status_line: false,
+    },
+    Binding {
+        key: "+",
+        label: "raise priority",
+        description: "raise the task's priority (none, L, M, H)",
+        in_status_line: false,
+    },
+    Binding {
+        key: "-",
+        label: "lower priority",
+        description: "lower the task's priority (H, M, L, none)",
+        in_status_line: false,
+    },
+    Binding {
+        key: "l",
+        label: "length",
+        description: "start the timer over with a length you choose",
+        in_status_line: false,
+    },
+    Binding {
+        key: "u",
+        label: "until",
+        description: "start the timer over, ending at a time you choose",
+        in_status_line: false,
+    },
+    Binding {
+        key: "/",
+        label: "search",
+        description: "search pending tasks and start one of them directly",
+        in_status_line: false,
+    },
+    Binding {
+        key: "P",
+        label: "project",
+        description: "only pick tasks from one project (and its subprojects)",
+        in_status_line: false,
+    },
+    Binding {
+        key: "i",
+        label: "info",
+        description: "show or hide everything about the task",
+        in_status_line: false,
+    },
+    Binding {
+        key: "s",
+        label: "stats",
+        description: "show or hide stats about the ready tasks",
+        in_status_line: false,
+    },
+    Binding {
+        key: "?",
+        label: "help",
+        description: "show or hide this help",
+        in_status_line: true,
+    },
+    Binding {
+        key: "Esc",
+        label: "close",
+        description: "close whatever is open on top of the timer",
+        in_status_line: false,
+    },
+];
+
+/// How many tasks aren't ready and why, for the nothing screen.
+#[derive(Debug)]
+struct NotReady {
+    checked: DateTime<Utc>,
+    waiting: usize,
+    blocked: usize,
+    scheduled: usize,
+}
+
+impl NotReady {
+    /// Something like "3 waiting, 2 blocked", leaving out anything with no
+    /// tasks. If every count is zero there's nothing to say.
+    fn summary(&self) -> Option<String> {
+        let parts = [
+            (self.waiting, "waiting"),
+            (self.blocked, "blocked"),
+            (self.scheduled, "scheduled later today"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, reason)| format!("{} {}", count, reason))
+        .collect_vec();
+
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+#[derive(Debug)]
+struct Pool {
+    fetched: DateTime<Utc>,
+    tasks: Vec<Task>,
+}
+
+#[expect(
+    clippy::large_enum_variant,
+    reason = "only one is used at a time; it does not dominate memory usage"
+)]
+#[derive(Debug)]
+pub enum Activity {
+    Nothing,
+    Task {
+        task: Task,
+        started: DateTime<Utc>,
+        length: Duration,
+        original_length: Duration,
+
+        /// How many times we've extended the timer with `m`.
+        extensions: u32,
+
+        /// Whether we set the length ourselves with `l` or `u` instead of
+        /// going with the estimate.
+        overridden: bool,
+    },
+}
+
+impl Activity {
+    pub fn is_nothing(&self) -> bool {
+        matches!(self, Self::Nothing)
+    }
+
+    /// How long we've been going past the end of the timer, if we have.
+    pub fn overtime(&self, now: DateTime<Utc>) -> Option<Duration> {
+        match self {
+            Self::Task { .. } => self
+                .ends_at()
+                .map(|end| now - end)
+                .filter(|over| *over > Duration::zero()),
+            Self::Nothing => None,
+        }
+    }
+
+    /// A one-line description of the activity for printing outside the UI.
+    pub fn summary(&self) -> String {
+        match self {
+            Self::Task { task, length, .. } => format!(
+                "{}: {} ({})",
+                task.id,
+                task.description,
+                format_hours_minutes(*length)
+            ),
+            Self::Nothing => "nothing ready".to_owned(),
+        }
+    }
+
+    /// Environment variables describing the current task, for scripts we
+    /// call that want more than the uuid we pass as an argument.
+    pub fn env(&self, now: DateTime<Utc>) -> Vec<(&'static str, String)> {
+        let Self::Task {
+            task,
+            started,
+            length,
+            ..
+        } = self
+        else {
+            return Vec::new();
+        };
+
+        vec![
+            ("TASKRAND_UUID", task.uuid.clone()),
+            ("TASKRAND_DESCRIPTION", task.description.clone()),
+            ("TASKRAND_PROJECT", task.project.clone().unwrap_or_default()),
+            (
+                "TASKRAND_REMAINING_SECONDS",
+                (*length - (now - *started)).num_seconds().to_string(),
+            ),
+        ]
+    }
+
+    /// When the timer runs out, if there is one (and it's not so far off
+    /// that we can't say when.)
+    pub fn ends_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Task {
+                started, length, ..
+            } => started.checked_add_signed(*length),
+            Self::Nothing => None,
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.ends_at().is_some_and(|end| end <= now)
+    }
+
+    pub async fn mark_done(&self, tw: &Taskwarrior) -> Result<()> {
+        if let Self::Task { task, .. } = self {
+            tw.mark_done(&task.uuid)
+                .await
+                .with_context(|| format!("could not mark task {} as done", task.id))?;
+        }
+
+        Ok(())
+    }
+
+    /// Push the timer back by `duration`, as if it hadn't been running.
+    pub fn pause_for(&mut self, duration: Duration) {
+        if let Self::Task { started, .. } = self {
+            *started += duration;
+        }
+    }
+
+    /// Start the timer over with exactly `length`.
+    pub fn restart(&mut self, new_length: Duration, now: DateTime<Utc>) {
+        if let Self::Task {
+            started,
+            length,
+            original_length,
+            extensions,
+            overridden,
+            ..
+        } = self
+        {
+            *started = now;
+            *length = new_length;
+            *original_length = new_length;
+            *extensions = 0;
+            *overridden = true;
+        }
+    }
+
+    /// Give ourselves more time. Normally this adds the original length
+    /// again, but with a positive `snap` we instead move the end of the timer
+    /// to the next wall-clock multiple of `snap` (e.g. the next quarter hour.)
+    pub fn extend(&mut self, snap: Duration, now: DateTime<Utc>) {
+        match self {
+            Self::Task {
+                started,
+                length,
+                original_length,
+                extensions,
+                ..
+            } => {
+                if snap > Duration::zero() {
+                    let end = started
+                        .checked_add_signed(*length)
+                        .map_or(now, |end| end.max(now));
+
+                    *length = next_boundary(end, snap, &Local) - *started;
+                } else if let Some(extended) = length.checked_add(original_length) {
+                    *length = extended;
+                }
+
+                *extensions += 1;
+            }
+            Self::Nothing => {}
+        }
+    }
+
+    /// Get the latest version of the task from Taskwarrior. If the task was
+    /// completed or deleted out from under us, we go back to doing nothing
+    /// and return the id of the task that's gone.
+    pub async fn refresh_task(&mut self, tw: &Taskwarrior) -> Result<Option<usize>> {
+        let Self::Task { task, .. } = self else {
+            return Ok(None);
+        };
+
+        let refreshed = tw
+            .export()
+            .with_filter(&task.uuid)
+            .with_filter("-COMPLETED")
+            .with_filter("-DELETED")
+            .with_filter("limit:1")
+            .call()
+            .await
+            .context("could not refresh task")?
+            .pop();
+
+        match refreshed {
+            Some(mut refreshed) => {
+                // Taskwarrior doesn't export this, so keep what the pool said.
+                refreshed.blocking = task.blocking;
+                *task = refreshed;
+
+                Ok(None)
+            }
+            None => {
+                let id = task.id;
+                tracing::info!(uuid = task.uuid, "task disappeared during refresh");
+
+                *self = Self::Nothing;
+
+                Ok(Some(id))
+            }
+        }
+    }
+}
+
+/// The projects that fuzzily match `query`: every character of the query
+/// appears in order, ignoring case. Projects containing the query as-is come
+/// first. With no query, `None` (meaning every project) comes first.
+fn project_matches<'a>(projects: &'a [String], query: &str) -> Vec<Option<&'a str>> {
+    if query.is_empty() {
+        return std::iter::once(None)
+            .chain(projects.iter().map(|project| Some(project.as_str())))
+            .collect();
+    }
+
+    let query = query.to_lowercase();
+
+    projects
+        .iter()
+        .filter(|project| {
+            let project = project.to_lowercase();
+            let mut chars = project.chars();
+
+            query.chars().all(|wanted| chars.any(|c| c == wanted))
+        })
+        .sorted_by_key(|project| !project.to_lowercase().contains(&query))
+        .map(|project| Some(project.as_str()))
+        .collect()
+}
+
+/// Whether `actual` is far enough from `planned` (as a fraction of `planned`)
+/// that it's worth offering to update the task's estimate.
+fn estimate_is_off(planned: Duration, actual: Duration, threshold: f64) -> bool {
+    if planned <= Duration::zero() {
+        return false;
+    }
+
+    let difference = (actual - planned).num_seconds().abs() as f64;
+
+    difference / planned.num_seconds() as f64 > threshold
+}
+
+/// The longest timer we'll set with `l`. Anything longer is almost certainly
+/// a typo.
+const MAX_LENGTH: Duration = Duration::hours(24);
+
+/// How many poll intervals can go by without a tick before we decide the
+/// computer was asleep. Slow Taskwarrior calls delay ticks too, so we also
+/// wait at least `MIN_SLEEP_GAP`.
+const SLEEP_GAP_POLLS: i32 = 10;
+const MIN_SLEEP_GAP: Duration = Duration::minutes(1);
+
+/// The age urgency coefficient to use in stale mode. Taskwarrior's default is
+/// 2.0, so this lets age outweigh nearly everything else.
+const STALE_AGE_COEFFICIENT: f64 = 20.0;
+
+/// Whether a word from an annotation looks like something we can open.
+fn is_link(word: &str) -> bool {
+    word.starts_with("http://") || word.starts_with("https://")
+}
+
+/// Every link in the task's annotations, in the order they're shown.
+fn links(task: &Task) -> Vec<String> {
+    task.annotations
+        .iter()
+        .flat_map(|annotation| annotation.description.split(' '))
+        .filter(|word| is_link(word))
+        .map(|word| word.to_owned())
+        .collect()
+}
+
+/// How many rows `line` takes up when word-wrapped to `width` columns. This
+/// measures display width rather than bytes or chars, so that wide characters
+/// (CJK, emoji) count for the two columns they actually take up.
+fn wrapped_height(line: &Line, width: u16) -> u16 {
+    let width = usize::from(width.max(1));
+    let text: String = line
+        .spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect();
+
+    let mut rows = 1;
+    let mut row_width = 0;
+
+    for word in text.split_inclusive(' ') {
+        let word_width = word.trim_end().width();
+
+        if row_width > 0 && row_width + word_width > width {
+            rows += 1;
+            row_width = 0;
+        }
+
+        if word_width > width {
+            // Words longer than a whole row get broken across rows.
+            rows += (word_width - 1) / width;
+            row_width = (word_width - 1) % width + 1;
+        } else {
+            row_width += word_width;
+        }
+
+        row_width += word.width() - word_width;
+    }
+
+    rows as u16
+}
+
+/// How long until `due`, or the date it's due (on the calendar in `tz`) if
+/// that's more than a couple of weeks away.
+fn format_due<Tz: TimeZone>(
+    due: DateTime<Utc>,
+    now: DateTime<Utc>,
+    date_format: &str,
+    tz: &Tz,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let remaining = due - now;
+
+    if remaining.num_seconds().abs() < 60 {
+        format!("{}s", remaining.num_seconds())
+    } else if remaining.num_minutes().abs() < 60 {
+        format!("{}m", remaining.num_minutes())
+    } else if remaining.num_hours().abs() < 24 {
+        format!("{}h", remaining.num_hours())
+    } else if remaining.num_days().abs() < 14 {
+        format!("{}d", remaining.num_days())
+    } else {
+        due.with_timezone(tz).format(date_format).to_string()
+    }
+}
+
+/// The next time the wall clock in `tz` shows `input` (like `15:00` or
+/// `3:30pm`), which is tomorrow if that time has already passed today.
+fn next_time_of_day<Tz: TimeZone>(
+    input: &str,
+    now: DateTime<Utc>,
+    tz: &Tz,
+) -> Result<DateTime<Utc>> {
+    let original = input.trim();
+    let mut input = original.to_uppercase();
+
+    // chrono needs minutes to make a time, so `3pm` becomes `3:00PM`.
+    if !input.contains(':') {
+        let hours = input.chars().take_while(char::is_ascii_digit).count();
+        input.insert_str(hours, ":00");
+    }
+
+    let time = ["%H:%M", "%I:%M%p", "%I:%M %p"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(&input, format).ok())
+        .with_context(|| format!("could not parse `{}` as a time of day", original))?;
+
+    let today = now.with_timezone(tz).date_naive();
+
+    for date in [today, today + Duration::days(1)] {
+        if let Some(end) = tz.from_local_datetime(&date.and_time(time)).earliest() {
+            let end = end.with_timezone(&Utc);
+            if end > now {
+                return Ok(end);
+            }
+        }
+    }
+
+    bail!("{} doesn't happen again in the next day", original)
+}
+
+/// The first multiple of `interval` on the wall clock in `tz` that comes
+/// strictly after `after`.
+fn next_boundary<Tz: TimeZone>(after: DateTime<Utc>, interval: Duration, tz: &Tz) -> DateTime<Utc> {
+    let offset = i64::from(after.with_timezone(tz).offset().fix().local_minus_utc());
+    let step = interval.num_seconds().max(1);
+
+    let local = after.timestamp() + offset;
+    let next = (local.div_euclid(step) + 1) * step;
+
+    DateTime::from_timestamp(next - offset, 0).unwrap_or(after + interval)
+}
+
+/// Whether the overtime flash should be showing right now. The flashing
+/// speeds up the longer we've been going over.
+fn flash_is_on(overtime: Duration) -> bool {
+    let period = if overtime < Duration::minutes(5) {
+        4
+    } else if overtime < Duration::minutes(15) {
+        2
+    } else {
+        1
+    };
+
+    (overtime.num_seconds() / period) % 2 == 1
+}
+
+/// The next priority up or down from `current`, with the empty string
+/// meaning no priority. Stays put at either end.
+fn step_priority(current: Option<&str>, raise: bool) -> &'static str {
+    const PRIORITIES: [&str; 4] = ["", "L", "M", "H"];
+
+    let index = PRIORITIES
+        .iter()
+        .position(|priority| Some(*priority) == current)
+        .unwrap_or(0);
+
+    if raise {
+        PRIORITIES[(index + 1).min(PRIORITIES.len() - 1)]
+    } else {
+        PRIORITIES[index.saturating_sub(1)]
+    }
+}
+
+fn priority_style(priority: &str) -> Style {
+    match priority {
+        "H" => Style::new()
+            .fg(tailwind::RED.c50)
+            .bg(tailwind::RED.c700)
+            .bold(),
+        "M" => Style::new()
+            .fg(tailwind::YELLOW.c950)
+            .bg(tailwind::YELLOW.c400)
+            .bold(),
+        _ => Style::new()
+            .fg(tailwind::SLATE.c50)
+            .bg(tailwind::SLATE.c500),
+    }
+}
+
+/// How long it would take to finish `ready` more tasks, going at the
+/// average pace of having finished `done` tasks in `elapsed`. We can't tell
+/// a pace until something is done.
+fn pool_eta(elapsed: Duration, done: usize, ready: usize) -> Option<Duration> {
+    if done == 0 {
+        return None;
+    }
+
+    Some(elapsed / done as i32 * ready as i32)
+}
+
+fn format_hours_minutes(duration: Duration) -> String {
+    if duration.num_hours() > 0 {
+        format!("{}h {}m", duration.num_hours(), duration.num_minutes() % 60)
+    } else {
+        format!("{}m", duration.num_minutes())
+    }
+}
+
+/// How much of `length` has gone by, from 0 to 1, for the timer gauge.
+fn elapsed_ratio(remaining: Duration, length: Duration) -> f64 {
+    // A zero-length timer would divide by zero, and the gauge panics on NaN.
+    if length <= Duration::zero() {
+        return 1.0;
+    }
+
+    1.0 - (remaining.num_seconds() as f64 / length.num_seconds() as f64).clamp(0.0, 1.0)
+}
+
+/// The annotation we add to record how long a task took.
+fn time_note(spent: Duration, visits: u32) -> String {
+    format!(
+        "task-rand: {} over {} {}",
+        format_hours_minutes(spent),
+        visits,
+        if visits == 1 { "session" } else { "sessions" }
+    )
+}
+
+fn format_remaining(remaining: Duration) -> String {
+    format!(
+        "{}{}:{:02}",
+        if remaining < Duration::zero() {
+            "-"
+        } else {
+            ""
+        },
+        remaining.abs().num_minutes(),
+        remaining.abs().num_seconds() % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::task::Annotation;
+    use crate::taskwarrior::shim;
+
+    #[test]
+    fn check_status_line_accepts_keys_and_extras() {
+        assert!(check_status_line(&default_status_line()).is_ok());
+        assert!(check_status_line(&["clock".to_owned(), "B".to_owned()]).is_ok());
+    }
+
+    #[test]
+    fn check_status_line_rejects_unknown_items() {
+        assert!(check_status_line(&["weather".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn every_binding_has_a_key_event() {
+        for binding in KEYS {
+            assert!(binding.key_event().is_some(), "{}", binding.key);
+        }
+    }
+
+    #[test]
+    fn binding_key_event_with_modifiers() {
+        let binding = KEYS.iter().find(|binding| binding.key == "Ctrl-b").unwrap();
+
+        assert_eq!(
+            binding.key_event(),
+            Some(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::CONTROL))
+        );
+    }
+
+    #[test]
+    fn estimate_is_off_when_much_longer() {
+        assert!(estimate_is_off(
+            Duration::minutes(20),
+            Duration::minutes(45),
+            0.5
+        ));
+    }
+
+    #[test]
+    fn estimate_is_off_when_much_shorter() {
+        assert!(estimate_is_off(
+            Duration::minutes(20),
+            Duration::minutes(5),
+            0.5
+        ));
+    }
+
+    #[test]
+    fn estimate_is_not_off_when_close() {
+        assert!(!estimate_is_off(
+            Duration::minutes(20),
+            Duration::minutes(25),
+            0.5
+        ));
+    }
+
+    #[test]
+    fn estimate_is_not_off_without_a_plan() {
+        assert!(!estimate_is_off(
+            Duration::zero(),
+            Duration::minutes(25),
+            0.5
+        ));
+    }
+
+    #[test]
+    fn time_note_one_session() {
+        assert_eq!(
+            time_note(Duration::minutes(25), 1),
+            "task-rand: 25m over 1 session"
+        );
+    }
+
+    #[test]
+    fn time_note_several_sessions() {
+        assert_eq!(
+            time_note(Duration::minutes(70), 3),
+            "task-rand: 1h 10m over 3 sessions"
+        );
+    }
+
+    #[test]
+    fn start_task_adds_up_time_spent() {
+        let started = Utc::now();
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+
+        let Activity::Task { task, .. } = task_activity(started, Duration::minutes(25)) else {
+            unreachable!()
+        };
+        let mut other = task.clone();
+        other.uuid = "other".to_owned();
+
+        app.doing = app.start_task(task.clone(), started);
+        app.doing = app.start_task(other.clone(), started + Duration::minutes(10));
+        app.doing = app.start_task(task.clone(), started + Duration::minutes(15));
+        app.doing = app.start_task(other, started + Duration::minutes(40));
+
+        assert_eq!(
+            app.time_spent.get(&task.uuid),
+            Some(&(Duration::minutes(35), 2))
+        );
+        assert_eq!(app.history, vec![Outcome::Left; 3]);
+    }
+
+    #[test]
+    fn format_remaining_zero() {
+        assert_eq!(format_remaining(Duration::zero()), "0:00");
+    }
+
+    #[test]
+    fn format_remaining_under_a_minute() {
+        assert_eq!(format_remaining(Duration::seconds(42)), "0:42");
+    }
+
+    #[test]
+    fn format_remaining_exactly_one_minute() {
+        assert_eq!(format_remaining(Duration::minutes(1)), "1:00");
+    }
+
+    #[test]
+    fn format_remaining_negative() {
+        assert_eq!(format_remaining(Duration::seconds(-75)), "-1:15");
+    }
+
+    #[test]
+    fn format_remaining_negative_under_a_minute() {
+        assert_eq!(format_remaining(Duration::seconds(-5)), "-0:05");
+    }
+
+    #[test]
+    fn format_remaining_large() {
+        assert_eq!(format_remaining(Duration::seconds(3 * 3600 + 7)), "180:07");
+    }
+
+    #[test]
+    fn elapsed_ratio_at_start() {
+        assert_eq!(
+            elapsed_ratio(Duration::minutes(25), Duration::minutes(25)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn elapsed_ratio_halfway() {
+        assert_eq!(
+            elapsed_ratio(Duration::minutes(10), Duration::minutes(20)),
+            0.5
+        );
+    }
+
+    #[test]
+    fn elapsed_ratio_at_end() {
+        assert_eq!(elapsed_ratio(Duration::zero(), Duration::minutes(25)), 1.0);
+    }
+
+    #[test]
+    fn elapsed_ratio_overtime_is_full() {
+        assert_eq!(
+            elapsed_ratio(Duration::minutes(-5), Duration::minutes(25)),
+            1.0
+        );
+    }
+
+    #[test]
+    fn elapsed_ratio_more_than_length_is_empty() {
+        assert_eq!(
+            elapsed_ratio(Duration::minutes(30), Duration::minutes(25)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn elapsed_ratio_zero_length() {
+        assert_eq!(elapsed_ratio(Duration::zero(), Duration::zero()), 1.0);
+    }
+
+    #[test]
+    fn step_priority_up_from_none() {
+        assert_eq!(step_priority(None, true), "L");
+    }
+
+    #[test]
+    fn step_priority_stays_at_high() {
+        assert_eq!(step_priority(Some("H"), true), "H");
+    }
+
+    #[test]
+    fn step_priority_down_to_none() {
+        assert_eq!(step_priority(Some("L"), false), "");
+    }
+
+    #[test]
+    fn project_matches_fuzzily() {
+        let projects = vec![
+            "home".to_owned(),
+            "work".to_owned(),
+            "work.backend".to_owned(),
+            "work.backend.api".to_owned(),
+            "work.apps".to_owned(),
+        ];
+
+        assert_eq!(
+            project_matches(&projects, "ap"),
+            vec![Some("work.backend.api"), Some("work.apps")]
+        );
+        assert_eq!(
+            project_matches(&projects, "WBE"),
+            vec![Some("work.backend"), Some("work.backend.api")]
+        );
+        assert!(project_matches(&projects, "xyz").is_empty());
+    }
+
+    #[test]
+    fn project_matches_nothing_typed_offers_every_project() {
+        let projects = vec!["home".to_owned()];
+
+        assert_eq!(project_matches(&projects, ""), vec![None, Some("home")]);
+    }
+
+    #[test]
+    fn pool_eta_at_the_session_pace() {
+        assert_eq!(
+            pool_eta(Duration::minutes(60), 3, 5),
+            Some(Duration::minutes(100))
+        );
+    }
+
+    #[test]
+    fn pool_eta_needs_a_finished_task() {
+        assert_eq!(pool_eta(Duration::minutes(60), 0, 5), None);
+    }
+
+    #[test]
+    fn spread_projects_weighs_down_the_last_project() {
+        let task = |uuid: &str, project: &str| Task {
+            project: Some(project.to_owned()),
+            ..test_task(uuid)
+        };
+
+        let settings = TaskRandConfig {
+            spread_projects: true,
+            recent_half_life: Duration::zero(),
+            ..TaskRandConfig::default()
+        };
+
+        let now = Utc::now();
+        let mut app =
+            App::new(Taskwarrior::new("task".into()), Config::default()).with_settings(settings);
+        app.doing = app.start_task(task("a", "work"), now);
+
+        assert_eq!(
+            app.weight(&task("b", "work"), now),
+            crate::weight::SAME_PROJECT_FACTOR
+        );
+        assert_eq!(app.weight(&task("c", "home"), now), 1.0);
+    }
+
+    #[test]
+    fn links_finds_urls_in_annotations() {
+        let task = test_task("00000000-0000-0000-0000-000000000000");
+        let annotation = |description: &str| Annotation {
+            entry: task.entry,
+            description: description.to_owned(),
+        };
+        let task = Task {
+            annotations: vec![
+                annotation("see https://example.com/a"),
+                annotation("no link here"),
+                annotation("http://example.com/b and more"),
+            ],
+            ..task
+        };
+
+        assert_eq!(
+            links(&task),
+            vec!["https://example.com/a", "http://example.com/b"]
+        );
+    }
+
+    #[test]
+    fn wrapped_height_fits_on_one_row() {
+        assert_eq!(wrapped_height(&Line::from("hello world"), 20), 1);
+    }
+
+    #[test]
+    fn wrapped_height_wraps_words() {
+        assert_eq!(wrapped_height(&Line::from("hello world"), 8), 2);
+    }
+
+    #[test]
+    fn wrapped_height_counts_wide_characters_twice() {
+        // Five CJK characters are ten columns wide.
+        assert_eq!(wrapped_height(&Line::from("日本語です 🎉🎉"), 10), 2);
+    }
+
+    /// Draw the app on a test terminal and return every cell's symbol, row by
+    /// row. Rows aren't separated, so keep sizes wide enough that what we look
+    /// for doesn't wrap.
+    fn render_to_string(app: &App, width: u16, height: u16) -> String {
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    /// Render `app` as of `now` on a 100x12 screen and compare every row
+    /// (minus trailing spaces) to `expected`. The clock is on 24-hour time so
+    /// the layout doesn't depend on the local timezone; `CLOCK` and `UNTIL`
+    /// in `expected` stand in for the current time and the end of the timer.
+    fn assert_screen(app: App, now: DateTime<Utc>, expected: &[&str]) {
+        let app = app.with_settings(TaskRandConfig {
+            clock: crate::task_rand_config::Clock::TwentyFourHour,
+            ..TaskRandConfig::default()
+        });
+        let time = |at: DateTime<Utc>| at.with_timezone(&Local).format("%H:%M").to_string();
+
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(100, 12)).unwrap();
+        terminal.draw(|frame| app.render_at(frame, now)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let rendered = (0..12)
+            .map(|y| {
+                (0..100)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_owned()
+            })
+            .collect_vec();
+
+        let expected = expected
+            .iter()
+            .map(|line| {
+                let line = line.replace("CLOCK", &time(now));
+                match app.doing.ends_at() {
+                    Some(end) => line.replace("UNTIL", &time(end)),
+                    None => line,
+                }
+            })
+            .collect_vec();
+
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn render_ascii_has_no_unicode() {
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default()).with_ascii(true);
+        app.doing = task_activity(Utc::now(), Duration::minutes(25));
+        app.history = vec![Outcome::Done, Outcome::Left];
+        app.streak = 1;
+
+        let rendered = render_to_string(&app, 120, 20);
+
+        assert!(rendered.is_ascii());
+        assert!(rendered.contains("* o"));
+
+        app.overlay = Some(Overlay::Stats);
+        let rendered = render_to_string(&app, 120, 20);
+
+        assert!(rendered.is_ascii(), "{}", rendered);
+        assert!(rendered.contains("+ Stats "), "{}", rendered);
+
+        app.overlay = Some(Overlay::Help);
+        let rendered = render_to_string(&app, 120, 40);
+
+        assert!(rendered.is_ascii(), "{}", rendered);
+    }
+
+    #[test]
+    fn render_ready_count_survives_invalidation() {
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.doing = task_activity(Utc::now(), Duration::minutes(25));
+
+        assert!(render_to_string(&app, 120, 20).contains("? ready"));
+
+        let Activity::Task { task, .. } = &app.doing else {
+            panic!("expected a task")
+        };
+        app.pool = Some(Pool {
+            fetched: Utc::now(),
+            tasks: vec![task.clone(), task.clone()],
+        });
+        app.invalidate_pool();
+
+        assert!(render_to_string(&app, 120, 20).contains("2 ready"));
+    }
+
+    #[test]
+    fn render_plain_task() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T14:00:00Z")
+            .unwrap()
+            .to_utc();
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.doing = task_activity(now - Duration::minutes(5), Duration::minutes(25));
+
+        assert_screen(
+            app,
+            now,
+            &[
+                "                                                                                               CLOCK",
+                "",
+                "                                        1: a task until UNTIL",
+                "",
+                "",
+                "",
+                "             ███████████████",
+                "             ███████████████                    20:00",
+                "             ███████████████",
+                "",
+                "",
+                "           done edit focus more time reroll quit wait 1h open breakdown ? help · ? ready",
+            ],
+        );
+    }
+
+    #[test]
+    fn render_task_with_everything() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T14:00:00Z")
+            .unwrap()
+            .to_utc();
+        let task = Task {
+            id: 12,
+            description: "ship it".to_owned(),
+            project: Some("work".to_owned()),
+            tags: HashSet::from(["deploy".to_owned()]),
+            due: Some(now + Duration::hours(3)),
+            udas: HashMap::from([("jira".to_owned(), serde_json::json!("ABC-123"))]),
+            ..test_task("00000000-0000-0000-0000-000000000000")
+        };
+
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.doing = app.start_task(task, now - Duration::minutes(5));
+
+        assert_screen(
+            app,
+            now,
+            &[
+                "                                                                                               CLOCK",
+                "",
+                "                      12 / ABC-123: ship it +deploy pro:work due:3h until UNTIL",
+                "",
+                "",
+                "",
+                "             ███████████████",
+                "             ███████████████                    20:00",
+                "             ███████████████",
+                "",
+                "",
+                "           done edit focus more time reroll quit wait 1h open breakdown ? help · ? ready",
+            ],
+        );
+    }
+
+    #[test]
+    fn render_nothing_ready_shows_filters() {
+        let app = App::new(
+            Taskwarrior::new("task".into()).with_context(Some("work".to_owned())),
+            Config::default(),
+        );
+
+        assert_screen(
+            app,
+            Utc::now(),
+            &[
+                "                                                                                               CLOCK",
+                "",
+                "             Nothing to do right now. No tasks match jirastatus.not:backlog +READY",
+                "             status.not:recurring -BLOCKED (context work). Press r to look again.",
+                "",
+                "",
+                "             ███████████████████████████████████████████████████████████████████████████",
+                "             ███████████████████████████████████0:00 ███████████████████████████████████",
+                "             ███████████████████████████████████████████████████████████████████████████",
+                "",
+                "",
+                "           done edit focus more time reroll quit wait 1h open breakdown ? help · ? ready",
+            ],
+        );
+    }
+
+    #[test]
+    fn render_wide_description() {
+        let task = Task {
+            description: "日本語のタスク 🎉 with a long description that needs to wrap 🚀🚀🚀"
+                .to_owned(),
+            ..test_task("00000000-0000-0000-0000-000000000000")
+        };
+
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.doing = app.start_task(task, Utc::now());
+
+        let rendered = render_to_string(&app, 40, 20);
+
+        assert!(rendered.contains("日"));
+        assert!(rendered.contains("🚀"));
+    }
+
+    /// A plain pending task. Use struct update syntax for anything else.
+    fn test_task(uuid: &str) -> Task {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "uuid": uuid,
+            "description": "a task",
+            "urgency": 1.0,
+            "entry": "20250101T000000Z",
+        }))
+        .unwrap()
+    }
+
+    fn task_activity(started: DateTime<Utc>, length: Duration) -> Activity {
+        Activity::Task {
+            task: test_task("00000000-0000-0000-0000-000000000000"),
+            started,
+            length,
+            original_length: length,
+            extensions: 0,
+            overridden: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn choose_next_task_skips_negative_weights() {
+        let task = |uuid: &str, due: Option<DateTime<Utc>>| Task {
+            due,
+            ..test_task(uuid)
+        };
+
+        let settings = TaskRandConfig {
+            due_boost_factor: -1.0,
+            ..TaskRandConfig::default()
+        };
+
+        let now = Utc::now();
+        let mut app =
+            App::new(Taskwarrior::new("task".into()), Config::default()).with_settings(settings);
+        app.pool = Some(Pool {
+            fetched: now,
+            tasks: vec![task("due", Some(now)), task("later", None)],
+        });
+
+        let Activity::Task { task, .. } = app.choose_next_task().await.unwrap() else {
+            panic!("expected a task")
+        };
+
+        assert_eq!(task.uuid, "later");
+        assert!(app.message.is_none());
+    }
+
+    #[tokio::test]
+    async fn choose_next_task_with_zero_weights_picks_at_random() {
+        let task = Task {
+            urgency: 0.0,
+            ..test_task("00000000-0000-0000-0000-000000000000")
+        };
+
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.pool = Some(Pool {
+            fetched: Utc::now(),
+            tasks: vec![task],
+        });
+
+        let activity = app.choose_next_task().await.unwrap();
+
+        assert!(!activity.is_nothing());
+        assert!(app.message.is_some());
+    }
+
+    #[test]
+    fn env_describes_the_task() {
+        let started = Utc::now();
+        let activity = task_activity(started, Duration::minutes(25));
+
+        assert_eq!(
+            activity.env(started + Duration::minutes(5)),
+            vec![
+                (
+                    "TASKRAND_UUID",
+                    "00000000-0000-0000-0000-000000000000".to_owned()
+                ),
+                ("TASKRAND_DESCRIPTION", "a task".to_owned()),
+                ("TASKRAND_PROJECT", String::new()),
+                ("TASKRAND_REMAINING_SECONDS", "1200".to_owned()),
+            ]
+        );
+        assert!(Activity::Nothing.env(started).is_empty());
+    }
+
+    #[tokio::test]
+    async fn handle_tick_waits_while_holding() {
+        let task = test_task("00000000-0000-0000-0000-000000000000");
+
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.pool = Some(Pool {
+            fetched: Utc::now(),
+            tasks: vec![task],
+        });
+        app.holding = true;
+
+        app.handle_tick().await.unwrap();
+        assert!(app.doing.is_nothing());
+
+        app.holding = false;
+        app.handle_tick().await.unwrap();
+        assert!(!app.doing.is_nothing());
+    }
+
+    #[tokio::test]
+    async fn plan_picks_each_task_once_back_to_back() {
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.pool = Some(Pool {
+            fetched: Utc::now(),
+            tasks: vec![test_task("a"), test_task("b")],
+        });
+
+        let plan = app.plan(3).await.unwrap();
+
+        assert_eq!(plan.len(), 2);
+        let Activity::Task { started, .. } = &plan[1] else {
+            panic!("expected a task")
+        };
+        assert_eq!(plan[0].ends_at(), Some(*started));
+
+        let uuids = plan
+            .iter()
+            .filter_map(|activity| match activity {
+                Activity::Task { task, .. } => Some(task.uuid.as_str()),
+                Activity::Nothing => None,
+            })
+            .sorted()
+            .collect_vec();
+        assert_eq!(uuids, vec!["a", "b"]);
+
+        assert_eq!(app.agenda(&plan).len(), 2);
+
+        // The cache is left alone, so an expired one can't bring picks back.
+        assert_eq!(app.pool.as_ref().unwrap().tasks.len(), 2);
+    }
+
+    #[test]
+    fn slept_after_a_long_gap() {
+        let now = Utc::now();
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+
+        assert_eq!(app.slept(now), None);
+        assert_eq!(app.slept(now + Duration::seconds(1)), None);
+        assert_eq!(
+            app.slept(now + Duration::minutes(31)),
+            Some(Duration::minutes(31) - Duration::seconds(1))
+        );
+    }
+
+    #[test]
+    fn slept_scales_with_the_poll_interval() {
+        let now = Utc::now();
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default())
+            .with_poll_interval(Duration::minutes(2));
+
+        assert_eq!(app.slept(now), None);
+        assert_eq!(app.slept(now + Duration::minutes(2)), None);
+        assert_eq!(
+            app.slept(now + Duration::minutes(32)),
+            Some(Duration::minutes(30))
+        );
+    }
+
+    #[test]
+    fn pause_for_pushes_the_timer_back() {
+        let started = Utc::now();
+        let mut activity = task_activity(started, Duration::minutes(25));
+
+        activity.pause_for(Duration::minutes(10));
+
+        assert_eq!(activity.ends_at(), Some(started + Duration::minutes(35)));
+    }
+
+    #[test]
+    fn not_ready_summary() {
+        let not_ready = NotReady {
+            checked: Utc::now(),
+            waiting: 3,
+            blocked: 0,
+            scheduled: 5,
+        };
+
+        assert_eq!(
+            not_ready.summary().as_deref(),
+            Some("3 waiting, 5 scheduled later today")
+        );
+    }
+
+    #[test]
+    fn not_ready_summary_with_nothing_to_say() {
+        let not_ready = NotReady {
+            checked: Utc::now(),
+            waiting: 0,
+            blocked: 0,
+            scheduled: 0,
+        };
+
+        assert_eq!(not_ready.summary(), None);
+    }
+
+    #[tokio::test]
+    async fn pick_once_with_nothing_ready() {
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.pool = Some(Pool {
+            fetched: Utc::now(),
+            tasks: Vec::new(),
+        });
+
+        assert!(app.pick_once().await.unwrap().is_nothing());
+        assert!(app.doing.is_nothing());
+    }
+
+    #[test]
+    fn take_notification_once_per_timer() {
+        let settings = TaskRandConfig {
+            terminal_notify: true,
+            ..TaskRandConfig::default()
+        };
+
+        let started = Utc::now();
+        let mut app =
+            App::new(Taskwarrior::new("task".into()), Config::default()).with_settings(settings);
+        app.doing = task_activity(started, Duration::minutes(25));
+
+        assert_eq!(app.take_notification(started), None);
+
+        let later = started + Duration::minutes(30);
+        assert!(app.take_notification(later).is_some());
+        assert_eq!(app.take_notification(later), None);
+
+        app.doing.extend(Duration::zero(), later);
+        let even_later = later + Duration::minutes(30);
+        assert!(app.take_notification(even_later).is_some());
+    }
+
+    #[test]
+    fn start_task_uses_the_configured_estimate_uda() {
+        let task = Task {
+            udas: HashMap::from([
+                ("estimate".to_owned(), serde_json::json!("20min")),
+                ("effort".to_owned(), serde_json::json!("10min")),
+            ]),
+            ..test_task("00000000-0000-0000-0000-000000000000")
+        };
+
+        let settings = TaskRandConfig {
+            estimate_uda: "effort".to_owned(),
+            ..TaskRandConfig::default()
+        };
+        let mut app =
+            App::new(Taskwarrior::new("task".into()), Config::default()).with_settings(settings);
+
+        let Activity::Task { length, .. } = app.start_task(task, Utc::now()) else {
+            panic!("expected a task")
+        };
+
+        assert_eq!(length, Duration::minutes(10));
+    }
+
+    #[test]
+    fn extend_adds_original_length() {
+        let started = Utc::now();
+        let mut activity = task_activity(started, Duration::minutes(25));
+
+        activity.extend(Duration::zero(), started);
+        activity.extend(Duration::zero(), started);
+
+        let Activity::Task {
+            length,
+            original_length,
+            extensions,
+            ..
+        } = activity
+        else {
+            panic!("expected a task")
+        };
+
+        assert_eq!(length, Duration::minutes(75));
+        assert_eq!(original_length, Duration::minutes(25));
+        assert_eq!(extensions, 2);
+    }
+
+    #[test]
+    fn extend_with_snap_ends_on_a_boundary() {
+        let started = "2025-06-01T12:03:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut activity = task_activity(started, Duration::minutes(25));
+
+        activity.extend(Duration::minutes(15), started);
+
+        let Activity::Task {
+            length,
+            original_length,
+            ..
+        } = activity
+        else {
+            panic!("expected a task")
+        };
+
+        // Every real time zone is offset from UTC by a multiple of 15
+        // minutes, so a local boundary is a UTC boundary too.
+        let end = started + length;
+        assert!(end > started + Duration::minutes(25));
+        assert_eq!(end.timestamp() % (15 * 60), 0);
+        assert_eq!(original_length, Duration::minutes(25));
+    }
+
+    #[test]
+    fn extend_with_snap_counts_from_now_in_overtime() {
+        let started = "2025-06-01T12:03:00Z".parse::<DateTime<Utc>>().unwrap();
+        let now = started + Duration::minutes(40);
+        let mut activity = task_activity(started, Duration::minutes(25));
+
+        activity.extend(Duration::minutes(15), now);
+
+        let Activity::Task { length, .. } = activity else {
+            panic!("expected a task")
+        };
+
+        assert!(started + length > now);
+    }
+
+    #[test]
+    fn restart_sets_a_new_length() {
+        let started = Utc::now();
+        let now = started + Duration::minutes(5);
+        let mut activity = task_activity(started, Duration::minutes(25));
+
+        activity.restart(Duration::minutes(40), now);
+
+        let Activity::Task {
+            started,
+            length,
+            original_length,
+            ..
+        } = activity
+        else {
+            panic!("expected a task")
+        };
+
+        assert_eq!(started, now);
+        assert_eq!(length, Duration::minutes(40));
+        assert_eq!(original_length, Duration::minutes(40));
+    }
+
+    #[tokio::test]
+    async fn interactive_commands_use_our_taskrc_and_data() {
+        let tw = Taskwarrior::new("task".into())
+            .with_taskrc(Some("/work/taskrc".into()))
+            .with_data_location(Some("/work/tasks".into()));
+        let mut app = App::new(tw, Config::default());
+        app.doing = task_activity(Utc::now(), Duration::minutes(25));
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('b')))
+            .await
+            .unwrap();
+        let (command, _) = app.take_interactive().unwrap();
+
+        let env: HashMap<_, _> = command.as_std().get_envs().collect();
+        assert_eq!(
+            env.get(std::ffi::OsStr::new("TASKRC")),
+            Some(&Some(std::ffi::OsStr::new("/work/taskrc")))
+        );
+        assert_eq!(
+            env.get(std::ffi::OsStr::new("TASKDATA")),
+            Some(&Some(std::ffi::OsStr::new("/work/tasks")))
+        );
+    }
+
+    #[tokio::test]
+    async fn length_prompt_rejects_enormous_lengths() {
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.doing = task_activity(Utc::now(), Duration::minutes(25));
+
+        for input in ["9999999999999999d", "100000000w", "25h"] {
+            app.submit_prompt(Prompt::Length, input).await.unwrap();
+
+            assert!(app.message.take().is_some(), "{}", input);
+            let Activity::Task { length, .. } = &app.doing else {
+                panic!("expected a task")
+            };
+            assert_eq!(*length, Duration::minutes(25), "{}", input);
+        }
+    }
+
+    #[tokio::test]
+    async fn length_prompt_marks_the_timer_as_set() {
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.doing = task_activity(Utc::now(), Duration::minutes(25));
+
+        assert!(!render_to_string(&app, 120, 20).contains("(set)"));
+
+        app.submit_prompt(Prompt::Length, "40min").await.unwrap();
+
+        assert!(render_to_string(&app, 120, 20).contains("(set)"));
+    }
+
+    #[test]
+    fn render_timer_that_never_ends() {
+        let mut app = App::new(Taskwarrior::new("task".into()), Config::default());
+        app.doing = task_activity(Utc::now(), Duration::MAX);
+
+        assert_eq!(app.doing.ends_at(), None);
+        assert!(!app.doing.is_expired(Utc::now()));
+        assert!(!render_to_string(&app, 120, 20).contains("until"));
+    }
+
+    #[test]
+    fn extend_nothing_is_a_no_op() {
+        let mut activity = Activity::Nothing;
+
+        activity.extend(Duration::minutes(15), Utc::now());
+
+        assert!(activity.is_nothing());
+    }
+
+    #[test]
+    fn next_boundary_rounds_up() {
+        let after = DateTime::parse_from_rfc3339("2025-01-01T14:07:30Z")
+            .unwrap()
+            .to_utc();
+
+        assert_eq!(
+            next_boundary(after, Duration::minutes(15), &Utc),
+            DateTime::parse_from_rfc3339("2025-01-01T14:15:00Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn next_boundary_skips_exact_boundary() {
+        let after = DateTime::parse_from_rfc3339("2025-01-01T14:15:00Z")
+            .unwrap()
+            .to_utc();
+
+        assert_eq!(
+            next_boundary(after, Duration::minutes(15), &Utc),
+            DateTime::parse_from_rfc3339("2025-01-01T14:30:00Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn next_boundary_uses_local_wall_clock() {
+        // 14:07 UTC is 19:37 in India (UTC+5:30), so the next hour there is
+        // 20:00, or 14:30 UTC.
+        let after = DateTime::parse_from_rfc3339("2025-01-01T14:07:00Z")
+            .unwrap()
+            .to_utc();
+        let india = chrono::FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap();
+
+        assert_eq!(
+            next_boundary(after, Duration::hours(1), &india),
+            DateTime::parse_from_rfc3339("2025-01-01T14:30:00Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn format_due_soon_is_relative() {
+        let now = Utc::now();
+
+        assert_eq!(
+            format_due(now + Duration::hours(3), now, "%Y-%m-%d", &Utc),
+            "3h"
+        );
+    }
+
+    #[test]
+    fn format_due_far_off_uses_the_local_calendar_day() {
+        // 02:00 UTC on June 1st is still the evening of May 31st in New York.
+        let due = DateTime::parse_from_rfc3339("2025-06-01T02:00:00Z")
+            .unwrap()
+            .to_utc();
+        let now = due - Duration::days(30);
+        let new_york = chrono::FixedOffset::west_opt(4 * 3600).unwrap();
+
+        assert_eq!(format_due(due, now, "%Y-%m-%d", &new_york), "2025-05-31");
+        assert_eq!(format_due(due, now, "%Y-%m-%d", &Utc), "2025-06-01");
+    }
+
+    #[test]
+    fn next_time_of_day_later_today() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T14:00:00Z")
+            .unwrap()
+            .to_utc();
+
+        assert_eq!(
+            next_time_of_day("15:30", now, &Utc).unwrap(),
+            now + Duration::minutes(90)
+        );
+        assert_eq!(
+            next_time_of_day("3:30pm", now, &Utc).unwrap(),
+            now + Duration::minutes(90)
+        );
+        assert_eq!(
+            next_time_of_day("4PM", now, &Utc).unwrap(),
+            now + Duration::hours(2)
+        );
+    }
+
+    #[test]
+    fn next_time_of_day_already_past_is_tomorrow() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T14:00:00Z")
+            .unwrap()
+            .to_utc();
+
+        assert_eq!(
+            next_time_of_day("9am", now, &Utc).unwrap(),
+            now + Duration::hours(19)
+        );
+    }
+
+    #[test]
+    fn next_time_of_day_uses_the_local_clock() {
+        // 14:00 UTC is 10:00 in New York.
+        let now = DateTime::parse_from_rfc3339("2025-06-01T14:00:00Z")
+            .unwrap()
+            .to_utc();
+        let new_york = chrono::FixedOffset::west_opt(4 * 3600).unwrap();
+
+        assert_eq!(
+            next_time_of_day("11:00", now, &new_york).unwrap(),
+            now + Duration::hours(1)
+        );
+    }
+
+    #[test]
+    fn next_time_of_day_rejects_nonsense() {
+        assert!(next_time_of_day("teatime", Utc::now(), &Utc).is_err());
+    }
+
+    #[tokio::test]
+    async fn refresh_doing_moves_on_from_a_task_that_left_the_pool() {
+        let edited = r#"{"id":1,"uuid":"edited","description":"edited","urgency":1.0,"entry":"20250101T000000Z"}"#;
+        let other = r#"{"id":2,"uuid":"other","description":"other","urgency":1.0,"entry":"20250101T000000Z"}"#;
+
+        // The edited task still exists, but only the other one is ready.
+        let dir = tempfile::tempdir().unwrap();
+        let bin = shim(
+            &dir,
+            &format!(
+                "case \"$*\" in *+READY*) echo '{}' ;; *) echo '{}' ;; esac",
+                other, edited
+            ),
+        );
+
+        let mut app = App::new(Taskwarrior::new(bin), Config::default());
+        let task: Task = serde_json::from_str(edited).unwrap();
+        app.doing = app.start_task(task, Utc::now());
+
+        app.refresh_doing().await.unwrap();
+
+        let Activity::Task { task, .. } = &app.doing else {
+            panic!("expected a task")
+        };
+        assert_eq!(task.uuid, "other");
+        assert_eq!(app.message.as_deref(), Some("task 1 is no longer ready"));
+    }
+
+    #[tokio::test]
+    async fn sync_blocking_counts_dependents_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let calls = dir.path().join("calls");
+        let blocked = r#"{"id":2,"uuid":"blocked","description":"blocked","urgency":1.0,"entry":"20250101T000000Z"}"#;
+        let bin = shim(
+            &dir,
+            &format!(
+                "echo >> {}\ncase \"$*\" in *depends.has:00000000-0000-0000-0000-000000000000*) echo '{}' ;; esac",
+                calls.display(),
+                blocked
+            ),
+        );
+
+        let mut app = App::new(Taskwarrior::new(bin), Config::default());
+        app.doing = task_activity(Utc::now(), Duration::minutes(25));
+
+        app.sync_blocking().await;
+
+        // Picking the same task again out of the pool loses the count.
+        app.doing = task_activity(Utc::now(), Duration::minutes(25));
+        app.sync_blocking().await;
+
+        let Activity::Task { task, .. } = &app.doing else {
+            panic!("expected a task")
+        };
+        assert_eq!(task.blocking, 1);
+        assert_eq!(std::fs::read_to_string(&calls).unwrap().lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn stats_loads_an_invalidated_pool() {
+        let dir = tempfile::tempdir().unwrap();
+        let ready = r#"{"id":1,"uuid":"ready","description":"ready","urgency":1.0,"entry":"20250101T000000Z"}"#;
+        let bin = shim(&dir, &format!("echo '{}'", ready));
+
+        let mut app = App::new(Taskwarrior::new(bin), Config::default());
+        app.invalidate_pool();
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('s')))
+            .await
+            .unwrap();
+
+        assert!(render_to_string(&app, 120, 20).contains("Ready: 1 tasks"));
+    }
+
+    #[tokio::test]
+    async fn sync_blocking_survives_a_failed_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = App::new(Taskwarrior::new(shim(&dir, "exit 1")), Config::default());
+        app.doing = task_activity(Utc::now(), Duration::minutes(25));
+
+        app.sync_blocking().await;
+
+        let Activity::Task { task, .. } = &app.doing else {
+            panic!("expected a task")
+        };
+        assert_eq!(task.blocking, 0);
+    }
 }
@@ -1,4 +1,4 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 
 #[derive(Debug, PartialEq)]
 pub struct Config {
@@ -19,14 +19,23 @@ impl Config {
     pub fn parse(input: &str) -> Result<Self> {
         let mut out = Config::default();
 
-        for line in input.split('\n') {
-            if !line.contains('=') {
+        for line in input.lines() {
+            let line = line.trim();
+
+            // Besides `key=value` lines, Taskwarrior can print informational
+            // headers (like which overrides are in effect) and the
+            // continuations of multi-line values. None of those are settings
+            // we care about.
+            if line.is_empty() || line.starts_with('#') {
                 continue;
             }
 
-            let mut kv = line.splitn(2, '=');
-            let k = kv.next().ok_or(anyhow!("Expected a key"))?;
-            let v = kv.next().ok_or(anyhow!("Expected a value"))?;
+            let Some((k, v)) = line.split_once('=') else {
+                continue;
+            };
+
+            let k = k.trim();
+            let v = unquote(v.trim());
 
             match k {
                 "urgency.age.coefficient" => {
@@ -49,6 +58,20 @@ impl Config {
     }
 }
 
+/// Strip one pair of matching quotes from around a value, if present.
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|rest| rest.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+
+    value
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self::new()
@@ -84,4 +107,36 @@ mod test {
 
         assert_eq!(config.urgency_due_coefficient, 2.5);
     }
+
+    #[test]
+    fn parse_realistic_show_output() {
+        let config = Config::parse(
+            "Configuration override rc.data.location=/tmp/tasks\n\
+             \n\
+             # a comment=with an equals sign\n\
+             alias.burndown=burndown.weekly\n\
+             report.next.filter=status:pending -WAITING limit:page\n\
+             urgency.age.coefficient = 2.0 \n\
+             urgency.age.max=\"180\"\n\
+             urgency.due.coefficient='12.0'\n\
+             report.long.description=All details of tasks\n  that wraps onto a second line\n\
+             uda.jira.label=Jira=ID\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.urgency_age_coefficient, 2.0);
+        assert_eq!(config.urgency_age_max, 180.0);
+        assert_eq!(config.urgency_due_coefficient, 12.0);
+    }
+
+    #[test]
+    fn parse_splits_on_first_equals() {
+        assert!(Config::parse("urgency.age.max=1=2").is_err());
+    }
+
+    #[test]
+    fn parse_unquote_mismatched_quotes() {
+        assert_eq!(unquote("\"2.5'"), "\"2.5'");
+        assert_eq!(unquote("\"\""), "");
+    }
 }
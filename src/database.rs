@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A SQLite database where we keep a long-term record of completed tasks.
+#[derive(Debug, Clone)]
+pub struct Database {
+    conn: Arc<Mutex<Connection>>,
+}
+
+/// A task we finished, and how long it took compared to what we planned.
+#[derive(Debug)]
+pub struct Completion {
+    pub uuid: String,
+    pub description: String,
+    pub project: Option<String>,
+    pub planned: Duration,
+    pub actual: Duration,
+    pub completed_at: DateTime<Utc>,
+}
+
+impl Database {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("could not open database at {}", path.display()))?;
+
+        Self::from_connection(conn)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS completions (
+                id INTEGER PRIMARY KEY,
+                uuid TEXT NOT NULL,
+                description TEXT NOT NULL,
+                project TEXT,
+                planned_seconds INTEGER NOT NULL,
+                actual_seconds INTEGER NOT NULL,
+                completed_at TEXT NOT NULL
+            );",
+        )
+        .context("could not create the completions table")?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Record a completion in the background so that a slow disk doesn't
+    /// hold up the UI. Failures are logged rather than returned.
+    pub fn record_completion(&self, completion: Completion) {
+        let db = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = db.insert_completion(&completion) {
+                tracing::error!(?err, ?completion, "could not record completion");
+            }
+        });
+    }
+
+    fn insert_completion(&self, completion: &Completion) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| anyhow::anyhow!("database lock was poisoned"))?;
+
+        conn.execute(
+            "INSERT INTO completions (uuid, description, project, planned_seconds, actual_seconds, completed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (
+                &completion.uuid,
+                &completion.description,
+                &completion.project,
+                completion.planned.num_seconds(),
+                completion.actual.num_seconds(),
+                completion.completed_at.to_rfc3339(),
+            ),
+        )
+        .context("could not insert completion")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insert_completion() {
+        let db = Database::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+
+        db.insert_completion(&Completion {
+            uuid: "abc".to_owned(),
+            description: "write tests".to_owned(),
+            project: None,
+            planned: Duration::minutes(25),
+            actual: Duration::minutes(30),
+            completed_at: Utc::now(),
+        })
+        .unwrap();
+
+        let actual: i64 = db
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT actual_seconds FROM completions", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+
+        assert_eq!(actual, 30 * 60);
+    }
+}
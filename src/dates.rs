@@ -1,3 +1,4 @@
+use anyhow::{anyhow, bail};
 use chrono::{DateTime, Duration, NaiveDateTime, Utc};
 use serde::de::{self, Visitor};
 use std::fmt;
@@ -67,17 +68,22 @@ impl Visitor<'_> for DurationVisitor {
     type Value = Option<Duration>;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a string in ISO8601 duration format or null")
+        formatter.write_str("a string in ISO8601 or Taskwarrior duration format or null")
     }
 
     fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        let parsed = iso8601_duration::Duration::parse(value)
-            .map_err(|error| de::Error::custom(format!("{error:?}")))?;
-
-        Ok(parsed.to_chrono())
+        // A bad estimate on one task shouldn't stop us from loading all the
+        // others, so we treat it like there's no estimate at all.
+        match parse_duration(value) {
+            Ok(duration) => Ok(Some(duration)),
+            Err(error) => {
+                tracing::warn!(value, %error, "ignoring unparseable duration");
+                Ok(None)
+            }
+        }
     }
 }
 
@@ -87,3 +93,132 @@ where
 {
     d.deserialize_str(DurationVisitor)
 }
+
+/// Parse a duration in either ISO8601 format (`PT30M`) or Taskwarrior's
+/// shorthand (`30min`, `2h`, `1.5d`.)
+pub fn parse_duration(input: &str) -> anyhow::Result<Duration> {
+    let input = input.trim();
+
+    if input.starts_with('P') {
+        let parsed = iso8601_duration::Duration::parse(input)
+            .map_err(|error| anyhow!("could not parse \"{}\": {:?}", input, error))?;
+
+        return parsed.to_chrono().ok_or_else(|| {
+            anyhow!(
+                "\"{}\" uses months or years, which don't have a fixed length",
+                input
+            )
+        });
+    }
+
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .ok_or_else(|| anyhow!("\"{}\" is missing a unit", input))?;
+    let (amount, unit) = input.split_at(split);
+
+    let amount: f64 = amount
+        .parse()
+        .map_err(|_| anyhow!("\"{}\" does not start with a number", input))?;
+
+    let seconds_per_unit = match unit.trim() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 3_600.0,
+        "d" | "day" | "days" => 86_400.0,
+        "w" | "wk" | "wks" | "week" | "weeks" => 604_800.0,
+        other => bail!("\"{}\" is not a unit I know about", other),
+    };
+
+    // We're OK with the conversion being naive here. Sensible estimates are
+    // nowhere near big enough to lose precision, and `as` saturates anything
+    // absurd so `try_seconds` can reject it.
+    Duration::try_seconds((amount * seconds_per_unit).round() as i64)
+        .ok_or_else(|| anyhow!("\"{}\" is too long", input))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_iso8601_minutes() {
+        assert_eq!(parse_duration("PT30M").unwrap(), Duration::minutes(30));
+    }
+
+    #[test]
+    fn parse_iso8601_hours_and_minutes() {
+        assert_eq!(parse_duration("PT1H30M").unwrap(), Duration::minutes(90));
+    }
+
+    #[test]
+    fn parse_iso8601_months_is_an_error() {
+        assert!(parse_duration("P1M").is_err());
+    }
+
+    #[test]
+    fn parse_taskwarrior_minutes() {
+        assert_eq!(parse_duration("30min").unwrap(), Duration::minutes(30));
+    }
+
+    #[test]
+    fn parse_taskwarrior_hours() {
+        assert_eq!(parse_duration("2h").unwrap(), Duration::hours(2));
+    }
+
+    #[test]
+    fn parse_taskwarrior_days() {
+        assert_eq!(parse_duration("1d").unwrap(), Duration::days(1));
+    }
+
+    #[test]
+    fn parse_taskwarrior_fractional() {
+        assert_eq!(parse_duration("1.5h").unwrap(), Duration::minutes(90));
+    }
+
+    #[test]
+    fn parse_missing_unit() {
+        assert!(parse_duration("30").is_err());
+    }
+
+    #[test]
+    fn parse_unknown_unit() {
+        assert!(parse_duration("3 fortnights").is_err());
+    }
+
+    #[test]
+    fn parse_too_long() {
+        assert!(parse_duration("9999999999999999d").is_err());
+    }
+
+    #[test]
+    fn parse_garbage() {
+        assert!(parse_duration("soon").is_err());
+    }
+
+    #[test]
+    fn deserialize_unparseable_estimate_as_none() {
+        #[derive(serde::Deserialize)]
+        struct Estimated {
+            #[serde(deserialize_with = "duration")]
+            estimate: Option<Duration>,
+        }
+
+        let parsed: Estimated = serde_json::from_str(r#"{"estimate": "soon"}"#).unwrap();
+
+        assert_eq!(parsed.estimate, None);
+    }
+
+    #[test]
+    fn deserialize_overflowing_estimate_as_none() {
+        #[derive(serde::Deserialize)]
+        struct Estimated {
+            #[serde(deserialize_with = "duration")]
+            estimate: Option<Duration>,
+        }
+
+        let parsed: Estimated =
+            serde_json::from_str(r#"{"estimate": "9999999999999999d"}"#).unwrap();
+
+        assert_eq!(parsed.estimate, None);
+    }
+}
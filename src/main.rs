@@ -1,43 +1,327 @@
 mod app;
 mod config;
+mod database;
 mod dates;
 mod task;
+mod task_rand_config;
 mod taskwarrior;
+mod timewarrior;
+mod weight;
 
 use crate::app::App;
+use crate::task_rand_config::TaskRandConfig;
 use anyhow::{bail, Context, Result};
-use clap::Parser;
+use chrono::Utc;
+use clap::{CommandFactory, Parser};
 use futures::StreamExt;
 use ratatui::DefaultTerminal;
+use std::io::Write;
 use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
 struct Cli {
-    #[clap(long, default_value = "task")]
-    task_bin: PathBuf,
+    /// The Taskwarrior binary to call [default: task]
+    #[clap(long)]
+    task_bin: Option<PathBuf>,
+
+    /// Use this profile from the config file instead of `default`
+    #[clap(long)]
+    profile: Option<String>,
+
+    /// Use this taskrc instead of the default one (passed as `rc:<path>`)
+    #[clap(long)]
+    taskrc: Option<PathBuf>,
+
+    /// Use this data location instead of the one in the taskrc (passed as
+    /// `rc.data.location=<path>`)
+    #[clap(long)]
+    data: Option<PathBuf>,
+
+    /// Only pick tasks visible in this Taskwarrior context
+    #[clap(long)]
+    context: Option<String>,
+
+    /// Only pick tasks that also match this Taskwarrior filter. Can be given
+    /// more than once.
+    #[clap(long = "filter")]
+    filters: Vec<String>,
+
+    /// Override an urgency coefficient for this run, like `due=0.5`. Can be
+    /// given more than once, and wins over the config file.
+    #[clap(long = "urgency-coeff", value_parser = parse_urgency_coefficient)]
+    urgency_coefficients: Vec<(String, f64)>,
+
+    /// Start in stale mode, which favors the oldest tasks (toggle with `a`)
+    #[clap(long)]
+    stale: bool,
+
+    /// Draw with plain ASCII instead of Unicode. Without this, we still use
+    /// ASCII if `TERM` or the locale suggest Unicode won't show up well.
+    #[clap(long)]
+    ascii: bool,
+
+    /// Pick one task, print it, and exit without starting the UI
+    #[clap(long)]
+    pick: bool,
+
+    /// Plan this many tasks back to back, print them with their times, and
+    /// exit without starting the UI. Use --seed to get the same plan again.
+    #[clap(long, value_name = "N")]
+    plan: Option<usize>,
+
+    /// Print every task that could be picked with its weight and chance of
+    /// being picked, then exit without starting the UI
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Seed the random number generator to make picks reproducible
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Read task-rand's settings from this TOML file instead of
+    /// `$XDG_CONFIG_HOME/task-rand/config.toml`
+    #[clap(long)]
+    config: Option<PathBuf>,
+
+    /// How often (in milliseconds) to redraw the timer. A shorter tick makes
+    /// the gauge smoother without calling Taskwarrior any more often.
+    #[clap(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    tick_ms: u64,
+
+    /// How often (in milliseconds) to check whether we need to pick a new
+    /// task, which can mean calling Taskwarrior
+    #[clap(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    poll_ms: u64,
+
+    /// Record completed tasks in a SQLite database at this path
+    #[clap(long)]
+    db: Option<PathBuf>,
+
+    /// Track time in Timewarrior while working on each task, using this
+    /// `timew` binary
+    #[clap(long)]
+    timew_bin: Option<PathBuf>,
+
+    /// Write logs to this file. Logging is off without it, since writing
+    /// to the terminal would garble the UI.
+    #[clap(long)]
+    log_file: Option<PathBuf>,
+
+    /// Which logs to write to --log-file, in `RUST_LOG` format
+    #[clap(long, default_value = "trace")]
+    log_level: String,
+
+    #[clap(subcommand)]
+    subcommand: Option<Commands>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Commands {
+    /// Print a shell completion script to stdout
+    Completions { shell: clap_complete::Shell },
 }
 
 impl Cli {
     async fn run(&self) -> Result<()> {
-        let tw = taskwarrior::Taskwarrior::new(self.task_bin.clone());
+        if let Some(Commands::Completions { shell }) = &self.subcommand {
+            clap_complete::generate(
+                *shell,
+                &mut Cli::command(),
+                env!("CARGO_PKG_NAME"),
+                &mut std::io::stdout(),
+            );
+
+            return Ok(());
+        }
+
+        self.init_logging().context("could not set up logging")?;
+
+        // A missing file is fine at the default path, but a path someone
+        // gave us on purpose had better exist.
+        let mut settings = match &self.config {
+            Some(path) => TaskRandConfig::load(path)?,
+            None => match TaskRandConfig::default_path() {
+                Some(path) => TaskRandConfig::load_if_exists(&path)?,
+                None => TaskRandConfig::default(),
+            },
+        };
+
+        if let Some(status_line) = &settings.status_line {
+            app::check_status_line(status_line)?;
+        }
+
+        let profile = settings.profile(self.profile.as_deref())?;
+        settings
+            .urgency_coefficients
+            .extend(profile.urgency_coefficients);
+        settings
+            .urgency_coefficients
+            .extend(self.urgency_coefficients.iter().cloned());
+
+        let context = self.context.clone().or(profile.context);
+
+        let tw = taskwarrior::Taskwarrior::new(
+            self.task_bin
+                .clone()
+                .or(profile.task_bin)
+                .unwrap_or_else(|| PathBuf::from("task")),
+        )
+        .with_taskrc(self.taskrc.clone().or(profile.taskrc))
+        .with_data_location(self.data.clone().or(profile.data))
+        .with_context(context.clone());
+
+        if let Some(context) = &context {
+            let contexts = tw
+                .contexts()
+                .await
+                .context("could not get taskwarrior contexts")?;
+
+            if !contexts.contains(context) {
+                bail!(
+                    "there is no taskwarrior context named \"{}\". Available contexts: {}",
+                    context,
+                    contexts.join(", ")
+                );
+            }
+        }
 
         let config = tw
             .config()
             .await
             .context("could not get taskwarrior config")?;
 
-        let app = App::new(tw, config);
+        let tw = tw.with_retries(settings.retries);
+
+        let database = self
+            .db
+            .as_deref()
+            .map(database::Database::open)
+            .transpose()
+            .context("could not open the completion database")?;
+
+        let mut app = App::new(tw, config)
+            .with_settings(settings)
+            .with_filters(
+                profile
+                    .filters
+                    .into_iter()
+                    .chain(self.filters.iter().cloned())
+                    .collect(),
+            )
+            .with_stale(self.stale)
+            .with_poll_interval(chrono::Duration::milliseconds(self.poll_ms as i64))
+            .with_ascii(self.ascii || !unicode_supported())
+            .with_seed(self.seed)
+            .with_database(database)
+            .with_timewarrior(self.timew_bin.clone().map(timewarrior::Timewarrior::new));
+
+        if self.pick {
+            let activity = app.pick_once().await.context("could not pick a task")?;
+
+            println!("{}", activity.summary());
+
+            return Ok(());
+        }
+
+        if let Some(count) = self.plan {
+            let plan = app.plan(count).await.context("could not plan tasks")?;
+
+            if plan.is_empty() {
+                println!("nothing ready");
+            }
+
+            for line in app.agenda(&plan) {
+                println!("{}", line);
+            }
+
+            return Ok(());
+        }
+
+        if self.dry_run {
+            let mut candidates = app
+                .candidates(Utc::now())
+                .await
+                .context("could not get candidate tasks")?;
+
+            candidates.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+            let urgency = app
+                .preview_urgency()
+                .await
+                .context("could not get urgency to preview")?;
+
+            let total: f64 = candidates.iter().map(|(_, weight)| weight).sum();
+
+            let count = candidates.len() as f64;
+
+            println!(
+                "{:>5} {:>8} {:>8} {:>7}  description",
+                "id", "urgency", "weight", "chance"
+            );
+            for (task, weight) in candidates {
+                // With no positive weights we pick uniformly at random.
+                let chance = if total > 0.0 {
+                    weight / total
+                } else {
+                    1.0 / count
+                };
 
-        let terminal = ratatui::init();
-        let result = self.run_ui(app, terminal).await;
-        ratatui::restore();
+                println!(
+                    "{:>5} {:>8.2} {:>8.2} {:>6.1}%  {}",
+                    task.id,
+                    urgency.get(&task.uuid).copied().unwrap_or(task.urgency),
+                    weight,
+                    chance * 100.0,
+                    task.description
+                );
+            }
+
+            return Ok(());
+        }
+
+        let terminal = init_terminal()?;
+        let result = self.run_ui(&mut app, terminal).await;
+        restore_terminal();
+
+        // run_ui can bail out from anywhere, so this has to happen out here
+        // for Timewarrior not to keep tracking after we've gone.
+        app.stop_time_log().await;
 
         result
     }
 
-    async fn run_ui(&self, mut app: App, mut terminal: DefaultTerminal) -> Result<()> {
+    fn init_logging(&self) -> Result<()> {
+        let Some(log_file) = &self.log_file else {
+            return Ok(());
+        };
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)
+            .with_context(|| format!("could not open {}", log_file.display()))?;
+
+        let filter = tracing_subscriber::EnvFilter::try_new(&self.log_level)
+            .with_context(|| format!("could not parse log level \"{}\"", self.log_level))?;
+
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::sync::Mutex::new(file))
+            .with_ansi(false)
+            .init();
+
+        Ok(())
+    }
+
+    async fn run_ui(&self, app: &mut App, mut terminal: DefaultTerminal) -> Result<()> {
         let mut events = crossterm::event::EventStream::new();
-        let mut ticks = tokio::time::interval(tokio::time::Duration::from_secs(1));
+        let mut renders = tokio::time::interval(tokio::time::Duration::from_millis(self.tick_ms));
+        let mut polls = tokio::time::interval(tokio::time::Duration::from_millis(self.poll_ms));
+
+        // If a short tick falls behind (e.g. while Taskwarrior is slow) there's
+        // no point in catching up on the redraws or polls we missed.
+        renders.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        polls.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
         app.handle_tick()
             .await
@@ -53,29 +337,42 @@ impl Cli {
                         .context("could not handle event")?;
                 }
 
-                _ = ticks.tick() => {
+                // We redraw at the top of the loop, so there's nothing else to
+                // do here.
+                _ = renders.tick() => {}
+
+                _ = polls.tick() => {
                     app.handle_tick()
                         .await
                         .context("could not handle tick")?;
                 }
             }
 
+            app.sync_time_log().await;
+            app.sync_blocking().await;
+
+            // This goes out between frames so it can't end up in the middle
+            // of ratatui's output.
+            if let Some(message) = app.take_notification(Utc::now()) {
+                notify_terminal(&message);
+            }
+
             if app.should_quit() {
                 break Ok(());
             }
 
-            if let Some(mut command) = app.take_interactive() {
-                ratatui::restore();
+            if let Some((mut command, refresh)) = app.take_interactive() {
+                restore_terminal();
 
                 let status = command.status().await.context("could not run command")?;
 
-                terminal = ratatui::init();
+                terminal = init_terminal()?;
 
                 if !status.success() {
                     bail!("command failed with exit code {:?}", status.code())
                 }
 
-                app.refresh_doing()
+                app.refresh(refresh)
                     .await
                     .context("could not refresh task after interactive session")?;
             }
@@ -83,6 +380,85 @@ impl Cli {
     }
 }
 
+fn init_terminal() -> Result<DefaultTerminal> {
+    let terminal = ratatui::init();
+
+    crossterm::execute!(std::io::stdout(), crossterm::event::EnableMouseCapture)
+        .context("could not enable mouse capture")?;
+
+    Ok(terminal)
+}
+
+fn restore_terminal() {
+    // We're on our way out of the UI, so there's nothing useful to do if this
+    // fails; ratatui::restore ignores its errors for the same reason.
+    let _ = crossterm::execute!(std::io::stdout(), crossterm::event::DisableMouseCapture);
+
+    ratatui::restore();
+}
+
+/// Whether this terminal can probably draw Unicode.
+fn unicode_supported() -> bool {
+    let term = std::env::var("TERM").ok();
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty());
+
+    looks_unicode(term.as_deref(), locale.as_deref())
+}
+
+/// Only a locale that says otherwise or a terminal that's known not to
+/// cope rules Unicode out. We give the benefit of the doubt when these
+/// aren't set.
+fn looks_unicode(term: Option<&str>, locale: Option<&str>) -> bool {
+    if matches!(term, Some("dumb" | "linux")) {
+        return false;
+    }
+
+    locale.is_none_or(|locale| {
+        let locale = locale.to_lowercase();
+        locale.contains("utf-8") || locale.contains("utf8")
+    })
+}
+
+/// Parse a `key=value` urgency coefficient override.
+fn parse_urgency_coefficient(input: &str) -> Result<(String, f64)> {
+    let Some((key, value)) = input.split_once('=') else {
+        bail!("expected key=value, like due=0.5")
+    };
+
+    let key = key.trim();
+    if key.is_empty() {
+        bail!("the coefficient needs a name, like due=0.5")
+    }
+
+    let value = value
+        .trim()
+        .parse()
+        .with_context(|| format!("could not parse `{}` as a number", value.trim()))?;
+
+    Ok((key.to_owned(), value))
+}
+
+/// Send a desktop notification through the terminal with OSC 9.
+fn notify_terminal(message: &str) {
+    let mut stdout = std::io::stdout();
+
+    // A notification isn't worth interrupting work over if this fails.
+    let _ = stdout
+        .write_all(osc9(message).as_bytes())
+        .and_then(|()| stdout.flush());
+}
+
+/// The OSC 9 escape sequence for `message`. Control characters are dropped,
+/// since an escape or bell in a task description would end the sequence early.
+fn osc9(message: &str) -> String {
+    let message: String = message.chars().filter(|c| !c.is_control()).collect();
+
+    format!("\x1b]9;{}\x07", message)
+}
+
 #[tokio::main]
 async fn main() {
     let app = Cli::parse();
@@ -92,3 +468,41 @@ async fn main() {
         std::process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_urgency_coefficient_pair() {
+        assert_eq!(
+            parse_urgency_coefficient("due=0.5").unwrap(),
+            ("due".to_owned(), 0.5)
+        );
+    }
+
+    #[test]
+    fn parse_urgency_coefficient_errors() {
+        assert!(parse_urgency_coefficient("due").is_err());
+        assert!(parse_urgency_coefficient("=0.5").is_err());
+        assert!(parse_urgency_coefficient("due=lots").is_err());
+    }
+
+    #[test]
+    fn looks_unicode_by_default() {
+        assert!(looks_unicode(None, None));
+        assert!(looks_unicode(Some("xterm-256color"), Some("en_US.UTF-8")));
+    }
+
+    #[test]
+    fn looks_unicode_not_in_plain_locales_or_consoles() {
+        assert!(!looks_unicode(Some("xterm-256color"), Some("C")));
+        assert!(!looks_unicode(Some("linux"), Some("en_US.UTF-8")));
+        assert!(!looks_unicode(Some("dumb"), None));
+    }
+
+    #[test]
+    fn osc9_drops_control_characters() {
+        assert_eq!(osc9("a\x1bb\x07c"), "\x1b]9;abc\x07");
+    }
+}
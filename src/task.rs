@@ -1,6 +1,6 @@
 use crate::config::Config;
 use chrono::{DateTime, Duration, Utc};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, serde::Deserialize, Clone)]
 pub struct Task {
@@ -10,10 +10,14 @@ pub struct Task {
 
     pub description: String,
 
+    pub status: Option<String>,
+
     pub urgency: f64,
 
     pub project: Option<String>,
 
+    pub priority: Option<String>,
+
     #[serde(deserialize_with = "crate::dates::tw_datetime")]
     pub entry: DateTime<Utc>,
 
@@ -21,10 +25,13 @@ pub struct Task {
     pub due: Option<DateTime<Utc>>,
 
     #[serde(default, deserialize_with = "crate::dates::tw_datetime_opt")]
-    pub target: Option<DateTime<Utc>>,
+    pub scheduled: Option<DateTime<Utc>>,
+
+    #[serde(default, deserialize_with = "crate::dates::tw_datetime_opt")]
+    pub wait: Option<DateTime<Utc>>,
 
-    #[serde(default, deserialize_with = "crate::dates::duration")]
-    pub estimate: Option<Duration>,
+    #[serde(default, deserialize_with = "crate::dates::tw_datetime_opt")]
+    pub target: Option<DateTime<Utc>>,
 
     #[serde(default)]
     pub tags: HashSet<String>,
@@ -32,17 +39,71 @@ pub struct Task {
     #[serde(default)]
     pub annotations: Vec<Annotation>,
 
-    pub jira: Option<String>,
+    /// UUIDs of the tasks this one is waiting on.
+    #[serde(default, deserialize_with = "depends")]
+    pub depends: Vec<String>,
+
+    /// How many pending tasks are waiting on this one. Taskwarrior only
+    /// exports dependencies in one direction, so we fill this in ourselves.
+    #[serde(skip)]
+    pub blocking: usize,
+
+    /// Everything else Taskwarrior exported, including UDAs whose names
+    /// depend on how each person has set things up.
+    #[serde(flatten)]
+    pub udas: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, serde::Deserialize, Clone)]
-pub struct Annotation {}
+pub struct Annotation {
+    #[serde(deserialize_with = "crate::dates::tw_datetime")]
+    pub entry: DateTime<Utc>,
+
+    pub description: String,
+}
 
 impl Task {
+    /// The value of a string UDA, if the task has one.
+    pub fn uda(&self, name: &str) -> Option<&str> {
+        self.udas.get(name).and_then(|value| value.as_str())
+    }
+
+    /// The value of a date UDA, if the task has one we can parse.
+    pub fn datetime_uda(&self, name: &str) -> Option<DateTime<Utc>> {
+        self.udas
+            .get(name)
+            .and_then(|value| crate::dates::tw_datetime(value).ok())
+    }
+
+    /// How long we expect the task to take, from the given duration UDA.
+    /// Anything we can't parse is treated as no estimate.
+    pub fn estimate(&self, uda: &str) -> Option<Duration> {
+        self.udas
+            .get(uda)
+            .and_then(|value| crate::dates::duration(value).ok().flatten())
+    }
+
+    /// Whether this task depends on any of the given (still pending) tasks.
+    pub fn is_blocked_by(&self, pending: &HashSet<String>) -> bool {
+        self.depends.iter().any(|uuid| pending.contains(uuid))
+    }
+
+    /// Recurring tasks have a template with `status:recurring` that
+    /// Taskwarrior makes concrete instances from. The template itself can't be
+    /// worked on or completed, so we never want to pick it.
+    pub fn is_recurrence_template(&self) -> bool {
+        self.status.as_deref() == Some("recurring")
+    }
+
+    #[expect(
+        unused_variables,
+        reason = "we're not recalculating urgency from due/target and age yet"
+    )]
     pub fn urgency_at(&self, when: DateTime<Utc>, config: &Config) -> f64 {
         self.urgency
     }
 
+    #[expect(dead_code, reason = "not wired into urgency_at yet")]
     fn base_due_urgency_at(&self, when: DateTime<Utc>) -> f64 {
         // We build on the base Taskwarrior due date calculation by adding a
         // couple of dates (listed above the cases.)
@@ -80,6 +141,7 @@ impl Task {
         }
     }
 
+    #[expect(dead_code, reason = "not wired into urgency_at yet")]
     fn base_age_urgency_at(&self, when: DateTime<Utc>, config: &Config) -> f64 {
         // We're OK with the conversion being naive here. We're pretty unlikely
         // to enounter high enough numbers that we couldn't convert with `as`.
@@ -92,3 +154,82 @@ impl Task {
         }
     }
 }
+
+/// Taskwarrior 2.6+ exports `depends` as an array of UUIDs, but older
+/// versions export a single comma-separated string.
+fn depends<'de, D>(d: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Depends {
+        List(Vec<String>),
+        Joined(String),
+    }
+
+    Ok(match serde::Deserialize::deserialize(d)? {
+        Depends::List(uuids) => uuids,
+        Depends::Joined(uuids) => uuids
+            .split(',')
+            .map(|uuid| uuid.trim().to_owned())
+            .filter(|uuid| !uuid.is_empty())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn task(depends: serde_json::Value) -> Task {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "uuid": "a",
+            "description": "test",
+            "urgency": 1.0,
+            "entry": "20250101T000000Z",
+            "depends": depends,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn datetime_uda() {
+        let task: Task = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "uuid": "a",
+            "description": "test",
+            "urgency": 1.0,
+            "entry": "20250101T000000Z",
+            "earliest": "20250601T120000Z",
+            "note": "not a date",
+        }))
+        .unwrap();
+
+        assert_eq!(
+            task.datetime_uda("earliest"),
+            Some("2025-06-01T12:00:00Z".parse().unwrap())
+        );
+        assert_eq!(task.datetime_uda("note"), None);
+        assert_eq!(task.datetime_uda("missing"), None);
+    }
+
+    #[test]
+    fn depends_as_array() {
+        assert_eq!(task(serde_json::json!(["b", "c"])).depends, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn depends_as_string() {
+        assert_eq!(task(serde_json::json!("b,c")).depends, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn blocked_by_pending_dependency() {
+        let task = task(serde_json::json!(["b"]));
+
+        assert!(task.is_blocked_by(&HashSet::from(["b".to_owned()])));
+        assert!(!task.is_blocked_by(&HashSet::from(["c".to_owned()])));
+    }
+}
@@ -0,0 +1,878 @@
+use crate::weight::{default_weights, Weight};
+use anyhow::{anyhow, Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::Duration;
+use ratatui::style::{palette::tailwind, Color, Style};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Settings that only matter to task-rand, as opposed to the Taskwarrior
+/// settings in `Config`. These live in a TOML file, by default
+/// `~/.config/task-rand/config.toml`.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskRandConfig {
+    /// How long (in seconds) to reuse the exported pool of available tasks
+    /// before asking Taskwarrior again.
+    #[serde(deserialize_with = "seconds")]
+    pub cache_ttl: Duration,
+
+    /// Ask before marking a task as done.
+    pub confirm_done: bool,
+
+    /// Leave out tasks whose `scheduled` date hasn't arrived yet.
+    pub hide_scheduled: bool,
+
+    /// A date UDA holding the earliest time to start each task. Tasks where
+    /// that's still in the future are left out. Off unless set.
+    pub not_before_uda: Option<String>,
+
+    /// Never pick a task that depends on unfinished work.
+    pub skip_blocked: bool,
+
+    /// When nothing is ready, ask Taskwarrior how many tasks are waiting,
+    /// blocked, or scheduled later today, and show that on the nothing
+    /// screen.
+    pub explain_nothing: bool,
+
+    /// Whether to show wall-clock times in 12- or 24-hour format.
+    pub clock: Clock,
+
+    /// A `chrono` format string for showing absolute dates.
+    #[serde(deserialize_with = "date_format")]
+    pub date_format: String,
+
+    /// A Taskwarrior date expression for how long to defer a task.
+    pub defer: String,
+
+    /// Which Raycast categories to block during a focus session started
+    /// with `f`.
+    pub focus_categories: Vec<String>,
+
+    /// How long (in minutes) it takes for a task we just showed to get back
+    /// half its chance of being picked again, when `weights` includes
+    /// `recency` (or, four times as long after a skip, `skipped`). Zero
+    /// turns this off.
+    #[serde(deserialize_with = "minutes")]
+    pub recent_half_life: Duration,
+
+    /// What to do on our own when the timer runs out.
+    pub auto_advance: AutoAdvance,
+
+    /// What to do with the timer when we notice the computer has been
+    /// asleep.
+    pub after_sleep: AfterSleep,
+
+    /// Flash the timer once we've gone over time.
+    pub flash_overtime: bool,
+
+    /// Send an OSC 9 notification when the timer runs out. iTerm2, WezTerm,
+    /// and Kitty show these, even over SSH, but other terminals may not.
+    pub terminal_notify: bool,
+
+    /// Ask for a note to annotate the task with before marking it done.
+    pub done_note: bool,
+
+    /// Annotate tasks we finish with how long we spent on them this session.
+    pub time_note: bool,
+
+    /// Tasks due within this many hours get their weight multiplied by
+    /// `due_boost_factor` when picking.
+    #[serde(deserialize_with = "hours")]
+    pub due_boost_window: Duration,
+    pub due_boost_factor: f64,
+
+    /// When positive, extending the timer moves its end to the next
+    /// wall-clock multiple of this many minutes instead of adding the
+    /// original length.
+    #[serde(deserialize_with = "minutes")]
+    pub extend_snap: Duration,
+
+    /// Ignore a reroll this many milliseconds after the last one, so a
+    /// double tap or key repeat doesn't blow through several tasks. Zero
+    /// turns this off.
+    #[serde(deserialize_with = "milliseconds")]
+    pub reroll_cooldown: Duration,
+
+    /// Make it less likely to pick a task from the same project as the last
+    /// one, so one busy project doesn't take over the whole session.
+    pub spread_projects: bool,
+
+    /// How to weigh each task when picking one, in order. Each strategy
+    /// multiplies the weight: `urgency`, `recency`, `skipped`, `due_boost`,
+    /// or `spread_projects`. Only `urgency` is on by default; `due_boost`
+    /// and `spread_projects` also turn on with their own settings.
+    pub weights: Vec<Weight>,
+
+    /// Bounds (in minutes) on how long a session can be, applied after the
+    /// task's estimate.
+    #[serde(deserialize_with = "some_minutes")]
+    pub length_min: Option<Duration>,
+    #[serde(deserialize_with = "some_minutes")]
+    pub length_max: Option<Duration>,
+
+    /// The UDA holding each task's ticket ID, which we show next to the
+    /// description.
+    pub ticket_uda: String,
+
+    /// The UDA holding how long each task should take, in ISO8601 or
+    /// Taskwarrior duration format.
+    pub estimate_uda: String,
+
+    /// After finishing a task, offer to set its estimate to how long it
+    /// actually took when that's off from the plan by more than
+    /// `estimate_prompt_threshold` (as a fraction of the plan, so 0.5 means
+    /// 50%.)
+    pub estimate_prompt: bool,
+    pub estimate_prompt_threshold: f64,
+
+    /// Only pick tasks that have all of these tags.
+    pub include_tags: Vec<String>,
+
+    /// Urgency coefficients to override when exporting tasks to pick from,
+    /// keyed by the part between `urgency.` and `.coefficient` (e.g.
+    /// `blocked`.) Anything set here is added to the defaults, which zero
+    /// out `blocked` and `blocking`.
+    #[serde(deserialize_with = "urgency_coefficients")]
+    pub urgency_coefficients: BTreeMap<String, f64>,
+
+    /// Urgency coefficients for the urgency `--dry-run` shows next to each
+    /// task's weight. These start from Taskwarrior's own settings rather
+    /// than `urgency_coefficients`, so by default the preview shows the
+    /// urgency you'd see in `task next`.
+    pub preview_urgency_coefficients: BTreeMap<String, f64>,
+
+    /// Named bundles of Taskwarrior settings, chosen with `--profile`. The
+    /// one named `default` applies when no profile is given.
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// How many more times to try talking to Taskwarrior when its database
+    /// is locked.
+    pub retries: u32,
+
+    /// What to show in the status line, in order: keys (like `d` or `?`) and
+    /// `stale`, `streak`, `ready`, or `clock`. Leave this out for the usual
+    /// set.
+    pub status_line: Option<Vec<String>>,
+
+    /// Colors for the UI.
+    pub theme: Theme,
+}
+
+impl TaskRandConfig {
+    /// Where we look for the config file if we aren't told otherwise.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_home.join("task-rand").join("config.toml"))
+    }
+
+    /// Load the config file at `path`, which has to exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        let input = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read config at {}", path.display()))?;
+
+        Self::parse(&input).with_context(|| format!("could not parse config at {}", path.display()))
+    }
+
+    /// Load the config file at `path` if there is one, or use the defaults if
+    /// not. This is for the default path, which most people won't create.
+    pub fn load_if_exists(path: &Path) -> Result<Self> {
+        if path.try_exists().unwrap_or(true) {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self> {
+        let config: Self = toml::from_str(input)?;
+
+        if let (Some(min), Some(max)) = (config.length_min, config.length_max) {
+            if min > max {
+                return Err(anyhow!(
+                    "length_min ({} minutes) is longer than length_max ({} minutes)",
+                    min.num_minutes(),
+                    max.num_minutes(),
+                ));
+            }
+        }
+
+        // These get joined with commas into the focus URL, so anything else
+        // would change which categories Raycast sees.
+        if let Some(category) = config.focus_categories.iter().find(|category| {
+            category.is_empty()
+                || !category
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        }) {
+            return Err(anyhow!(
+                "focus category \"{}\" can only have letters, numbers, - and _",
+                category
+            ));
+        }
+
+        Ok(config)
+    }
+
+    /// Find the profile to use. Asking for a profile that doesn't exist is an
+    /// error, but having no `default` profile just means an empty one.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        match name {
+            Some(name) => self.profiles.get(name).cloned().ok_or_else(|| {
+                anyhow!(
+                    "there is no profile named \"{}\". Available profiles: {}",
+                    name,
+                    self.profiles
+                        .keys()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }),
+            None => Ok(self.profiles.get("default").cloned().unwrap_or_default()),
+        }
+    }
+}
+
+/// Settings for one Taskwarrior setup (e.g. work or personal.) Anything given
+/// on the command line takes precedence over these.
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub task_bin: Option<PathBuf>,
+    pub taskrc: Option<PathBuf>,
+    pub data: Option<PathBuf>,
+    pub context: Option<String>,
+
+    /// Extra filters for the pool, added before any from `--filter`.
+    pub filters: Vec<String>,
+
+    /// Added on top of the top-level `urgency_coefficients`.
+    pub urgency_coefficients: BTreeMap<String, f64>,
+}
+
+impl Default for TaskRandConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl: Duration::seconds(5),
+            confirm_done: false,
+            hide_scheduled: false,
+            not_before_uda: None,
+            skip_blocked: true,
+            explain_nothing: false,
+            clock: Clock::TwelveHour,
+            date_format: "%Y-%m-%d".to_owned(),
+            defer: "tomorrow".to_owned(),
+            focus_categories: vec![
+                "messaging".to_owned(),
+                "social".to_owned(),
+                "news".to_owned(),
+            ],
+            recent_half_life: Duration::minutes(30),
+            auto_advance: AutoAdvance::Off,
+            after_sleep: AfterSleep::Ignore,
+            flash_overtime: false,
+            terminal_notify: false,
+            done_note: false,
+            time_note: false,
+            due_boost_window: Duration::hours(24),
+            due_boost_factor: 1.0,
+            extend_snap: Duration::zero(),
+            reroll_cooldown: Duration::zero(),
+            spread_projects: false,
+            weights: default_weights(),
+            length_min: None,
+            length_max: None,
+            ticket_uda: "jira".to_owned(),
+            estimate_uda: "estimate".to_owned(),
+            estimate_prompt: false,
+            estimate_prompt_threshold: 0.5,
+            include_tags: Vec::new(),
+            urgency_coefficients: default_urgency_coefficients(),
+            preview_urgency_coefficients: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+            retries: 2,
+            status_line: None,
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// Blocked tasks aren't ready and blocking ones are boosted by Taskwarrior
+/// already, so by default we keep both from skewing the weights.
+fn default_urgency_coefficients() -> BTreeMap<String, f64> {
+    BTreeMap::from([("blocked".to_owned(), 0.0), ("blocking".to_owned(), 0.0)])
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+pub enum Clock {
+    #[serde(rename = "12h")]
+    TwelveHour,
+
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+}
+
+impl Clock {
+    /// A `chrono` format string for a time of day on this clock.
+    pub fn time_format(&self) -> &'static str {
+        match self {
+            Self::TwelveHour => "%-I:%M %P",
+            Self::TwentyFourHour => "%H:%M",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// The timer gauge (and status line) while there's time left.
+    #[serde(deserialize_with = "style")]
+    pub normal_gauge: Style,
+
+    /// The timer gauge once we've gone over time.
+    #[serde(deserialize_with = "style")]
+    pub overtime_gauge: Style,
+
+    /// Due dates that have already passed.
+    #[serde(deserialize_with = "style")]
+    pub overdue: Style,
+
+    /// Keys in the status line and help.
+    #[serde(deserialize_with = "style")]
+    pub accent: Style,
+}
+
+impl Theme {
+    pub fn gauge_style(&self, overtime: bool) -> Style {
+        if overtime {
+            self.overtime_gauge
+        } else {
+            self.normal_gauge
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            normal_gauge: Style::new().fg(tailwind::BLUE.c800).bg(tailwind::BLUE.c400),
+            overtime_gauge: Style::new()
+                .fg(tailwind::GREEN.c800)
+                .bg(tailwind::GREEN.c400),
+            overdue: Style::new().fg(tailwind::RED.c800),
+            accent: Style::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterSleep {
+    /// Count the time asleep like any other time.
+    Ignore,
+
+    /// Stop the timer while we were asleep.
+    Pause,
+
+    /// Pick something new, since whatever we were doing is probably stale.
+    Reroll,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoAdvance {
+    /// Keep counting into overtime until we get a key press.
+    #[serde(alias = "no")]
+    Off,
+
+    /// Pick something else without touching the expired task.
+    Reroll,
+
+    /// Mark the expired task as done and pick something else.
+    Done,
+}
+
+/// Parse a style the same way Taskwarrior's color settings look: `red`,
+/// `red on black`, or `on black`. Colors are anything ratatui understands,
+/// including hex (`#ff0000`) and indexed (`202`) colors.
+fn parse_style(input: &str) -> Result<Style> {
+    let color = |name: &str| {
+        Color::from_str(name.trim()).map_err(|_| anyhow!("\"{}\" is not a color", name.trim()))
+    };
+
+    let words = input.split_whitespace().collect::<Vec<_>>();
+    let (fg, bg) = match words.iter().position(|word| *word == "on") {
+        Some(on) => (words[..on].join(" "), Some(words[on + 1..].join(" "))),
+        None => (words.join(" "), None),
+    };
+
+    let mut style = Style::new();
+
+    if !fg.is_empty() {
+        style = style.fg(color(&fg)?);
+    }
+
+    if let Some(bg) = bg {
+        style = style.bg(color(&bg)?);
+    }
+
+    Ok(style)
+}
+
+/// Check that a date format is valid before we try to render with it, since
+/// `chrono` panics when displaying an invalid format.
+fn parse_date_format(input: &str) -> Result<String> {
+    if StrftimeItems::new(input).any(|item| item == Item::Error) {
+        return Err(anyhow!("\"{}\" is not a valid date format", input));
+    }
+
+    Ok(input.to_owned())
+}
+
+fn style<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Style, D::Error> {
+    parse_style(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+fn date_format<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    parse_date_format(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+fn milliseconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    i64::deserialize(deserializer).map(Duration::milliseconds)
+}
+
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    i64::deserialize(deserializer).map(Duration::seconds)
+}
+
+fn minutes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    i64::deserialize(deserializer).map(Duration::minutes)
+}
+
+fn some_minutes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    minutes(deserializer).map(Some)
+}
+
+fn urgency_coefficients<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, f64>, D::Error> {
+    let mut coefficients = default_urgency_coefficients();
+    coefficients.extend(BTreeMap::<String, f64>::deserialize(deserializer)?);
+
+    Ok(coefficients)
+}
+
+fn hours<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    i64::deserialize(deserializer).map(Duration::hours)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_empty_string() {
+        assert_eq!(
+            TaskRandConfig::parse("").unwrap(),
+            TaskRandConfig::default()
+        )
+    }
+
+    #[test]
+    fn parse_unknown_key() {
+        assert!(TaskRandConfig::parse("colour = 'red'").is_err());
+    }
+
+    #[test]
+    fn parse_malformed() {
+        assert!(TaskRandConfig::parse("confirm_done = ").is_err());
+    }
+
+    #[test]
+    fn load_missing_file_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(TaskRandConfig::load(&dir.path().join("config.toml")).is_err());
+    }
+
+    #[test]
+    fn load_if_exists_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(
+            TaskRandConfig::load_if_exists(&dir.path().join("config.toml")).unwrap(),
+            TaskRandConfig::default()
+        );
+    }
+
+    #[test]
+    fn load_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "confirm_done = true").unwrap();
+
+        assert!(TaskRandConfig::load(&path).unwrap().confirm_done);
+    }
+
+    #[test]
+    fn parse_cache_ttl() {
+        let config = TaskRandConfig::parse("cache_ttl = 30").unwrap();
+
+        assert_eq!(config.cache_ttl, Duration::seconds(30));
+    }
+
+    #[test]
+    fn parse_confirm_done() {
+        let config = TaskRandConfig::parse("confirm_done = true").unwrap();
+
+        assert!(config.confirm_done);
+    }
+
+    #[test]
+    fn parse_invalid_bool() {
+        assert!(TaskRandConfig::parse("confirm_done = 'maybe'").is_err());
+    }
+
+    #[test]
+    fn parse_skip_blocked() {
+        assert!(TaskRandConfig::default().skip_blocked);
+
+        let config = TaskRandConfig::parse("skip_blocked = false").unwrap();
+
+        assert!(!config.skip_blocked);
+    }
+
+    #[test]
+    fn parse_weights() {
+        let config = TaskRandConfig::parse("weights = ['urgency', 'due_boost']").unwrap();
+
+        assert_eq!(config.weights, vec![Weight::Urgency, Weight::DueBoost]);
+    }
+
+    #[test]
+    fn parse_unknown_weight_is_an_error() {
+        assert!(TaskRandConfig::parse("weights = ['vibes']").is_err());
+    }
+
+    #[test]
+    fn parse_focus_categories() {
+        let config = TaskRandConfig::parse("focus_categories = ['social', 'gaming']").unwrap();
+
+        assert_eq!(config.focus_categories, vec!["social", "gaming"]);
+    }
+
+    #[test]
+    fn parse_focus_categories_with_commas_is_an_error() {
+        assert!(TaskRandConfig::parse("focus_categories = ['social,news']").is_err());
+        assert!(TaskRandConfig::parse("focus_categories = ['']").is_err());
+    }
+
+    #[test]
+    fn parse_explain_nothing() {
+        let config = TaskRandConfig::parse("explain_nothing = true").unwrap();
+
+        assert!(config.explain_nothing);
+    }
+
+    #[test]
+    fn parse_not_before_uda() {
+        assert_eq!(TaskRandConfig::default().not_before_uda, None);
+
+        let config = TaskRandConfig::parse("not_before_uda = 'earliest'").unwrap();
+
+        assert_eq!(config.not_before_uda.as_deref(), Some("earliest"));
+    }
+
+    #[test]
+    fn parse_hide_scheduled() {
+        let config = TaskRandConfig::parse("hide_scheduled = true").unwrap();
+
+        assert!(config.hide_scheduled);
+    }
+
+    #[test]
+    fn parse_clock() {
+        let config = TaskRandConfig::parse("clock = '24h'").unwrap();
+
+        assert_eq!(config.clock, Clock::TwentyFourHour);
+    }
+
+    #[test]
+    fn parse_invalid_clock() {
+        assert!(TaskRandConfig::parse("clock = '13h'").is_err());
+    }
+
+    #[test]
+    fn parse_date_format() {
+        let config = TaskRandConfig::parse("date_format = '%d.%m.%Y'").unwrap();
+
+        assert_eq!(config.date_format, "%d.%m.%Y");
+    }
+
+    #[test]
+    fn parse_invalid_date_format() {
+        assert!(TaskRandConfig::parse("date_format = '%Q'").is_err());
+    }
+
+    #[test]
+    fn parse_defer() {
+        let config = TaskRandConfig::parse("defer = 'monday+8h'").unwrap();
+
+        assert_eq!(config.defer, "monday+8h");
+    }
+
+    #[test]
+    fn parse_recent_half_life() {
+        let config = TaskRandConfig::parse("recent_half_life = 10").unwrap();
+
+        assert_eq!(config.recent_half_life, Duration::minutes(10));
+    }
+
+    #[test]
+    fn parse_auto_advance() {
+        let config = TaskRandConfig::parse("auto_advance = 'reroll'").unwrap();
+
+        assert_eq!(config.auto_advance, AutoAdvance::Reroll);
+    }
+
+    #[test]
+    fn parse_after_sleep() {
+        let config = TaskRandConfig::parse("after_sleep = 'pause'").unwrap();
+
+        assert_eq!(config.after_sleep, AfterSleep::Pause);
+    }
+
+    #[test]
+    fn parse_auto_advance_no() {
+        let config = TaskRandConfig::parse("auto_advance = 'no'").unwrap();
+
+        assert_eq!(config.auto_advance, AutoAdvance::Off);
+    }
+
+    #[test]
+    fn parse_terminal_notify() {
+        let config = TaskRandConfig::parse("terminal_notify = true").unwrap();
+
+        assert!(config.terminal_notify);
+    }
+
+    #[test]
+    fn parse_flash_overtime() {
+        let config = TaskRandConfig::parse("flash_overtime = true").unwrap();
+
+        assert!(config.flash_overtime);
+    }
+
+    #[test]
+    fn parse_done_note() {
+        let config = TaskRandConfig::parse("done_note = true").unwrap();
+
+        assert!(config.done_note);
+    }
+
+    #[test]
+    fn parse_time_note() {
+        let config = TaskRandConfig::parse("time_note = true").unwrap();
+
+        assert!(config.time_note);
+    }
+
+    #[test]
+    fn parse_due_boost() {
+        let config = TaskRandConfig::parse("due_boost_window = 4\ndue_boost_factor = 3.0").unwrap();
+
+        assert_eq!(config.due_boost_window, Duration::hours(4));
+        assert_eq!(config.due_boost_factor, 3.0);
+    }
+
+    #[test]
+    fn parse_theme_foreground() {
+        let config = TaskRandConfig::parse("[theme]\noverdue = 'magenta'").unwrap();
+
+        assert_eq!(config.theme.overdue, Style::new().fg(Color::Magenta));
+    }
+
+    #[test]
+    fn parse_theme_foreground_and_background() {
+        let config = TaskRandConfig::parse("[theme]\nnormal_gauge = 'white on #336699'").unwrap();
+
+        assert_eq!(
+            config.theme.normal_gauge,
+            Style::new()
+                .fg(Color::White)
+                .bg(Color::Rgb(0x33, 0x66, 0x99))
+        );
+    }
+
+    #[test]
+    fn parse_theme_background_only() {
+        let config = TaskRandConfig::parse("[theme]\naccent = 'on blue'").unwrap();
+
+        assert_eq!(config.theme.accent, Style::new().bg(Color::Blue));
+    }
+
+    #[test]
+    fn parse_theme_keeps_unset_defaults() {
+        let config = TaskRandConfig::parse("[theme]\naccent = 'on blue'").unwrap();
+
+        assert_eq!(config.theme.overdue, Theme::default().overdue);
+    }
+
+    #[test]
+    fn parse_theme_invalid_color() {
+        assert!(TaskRandConfig::parse("[theme]\naccent = 'plaid'").is_err());
+    }
+
+    #[test]
+    fn parse_extend_snap() {
+        let config = TaskRandConfig::parse("extend_snap = 15").unwrap();
+
+        assert_eq!(config.extend_snap, Duration::minutes(15));
+    }
+
+    #[test]
+    fn parse_length_bounds() {
+        let config = TaskRandConfig::parse("length_min = 15\nlength_max = 45").unwrap();
+
+        assert_eq!(config.length_min, Some(Duration::minutes(15)));
+        assert_eq!(config.length_max, Some(Duration::minutes(45)));
+    }
+
+    #[test]
+    fn parse_length_min_over_max_is_an_error() {
+        assert!(TaskRandConfig::parse("length_min = 45\nlength_max = 15").is_err());
+    }
+
+    #[test]
+    fn parse_reroll_cooldown() {
+        let config = TaskRandConfig::parse("reroll_cooldown = 500").unwrap();
+
+        assert_eq!(config.reroll_cooldown, Duration::milliseconds(500));
+    }
+
+    #[test]
+    fn parse_status_line() {
+        let config = TaskRandConfig::parse("status_line = ['d', 'r', 'clock']").unwrap();
+
+        assert_eq!(
+            config.status_line,
+            Some(vec!["d".to_owned(), "r".to_owned(), "clock".to_owned()])
+        );
+    }
+
+    #[test]
+    fn parse_spread_projects() {
+        let config = TaskRandConfig::parse("spread_projects = true").unwrap();
+
+        assert!(config.spread_projects);
+    }
+
+    #[test]
+    fn parse_retries() {
+        let config = TaskRandConfig::parse("retries = 5").unwrap();
+
+        assert_eq!(config.retries, 5);
+    }
+
+    #[test]
+    fn parse_ticket_uda() {
+        let config = TaskRandConfig::parse("ticket_uda = 'linear'").unwrap();
+
+        assert_eq!(config.ticket_uda, "linear");
+    }
+
+    #[test]
+    fn parse_estimate_uda() {
+        let config = TaskRandConfig::parse("estimate_uda = 'effort'").unwrap();
+
+        assert_eq!(config.estimate_uda, "effort");
+    }
+
+    #[test]
+    fn parse_estimate_prompt() {
+        let config =
+            TaskRandConfig::parse("estimate_prompt = true\nestimate_prompt_threshold = 0.25")
+                .unwrap();
+
+        assert!(config.estimate_prompt);
+        assert_eq!(config.estimate_prompt_threshold, 0.25);
+    }
+
+    #[test]
+    fn parse_include_tags() {
+        let config = TaskRandConfig::parse("include_tags = ['quickwin', 'work']").unwrap();
+
+        assert_eq!(config.include_tags, vec!["quickwin", "work"]);
+    }
+
+    #[test]
+    fn parse_urgency_coefficients() {
+        let config =
+            TaskRandConfig::parse("[urgency_coefficients]\nblocking = 1.0\nage = 0.0").unwrap();
+
+        assert_eq!(
+            config.urgency_coefficients,
+            BTreeMap::from([
+                ("age".to_owned(), 0.0),
+                ("blocked".to_owned(), 0.0),
+                ("blocking".to_owned(), 1.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_preview_urgency_coefficients() {
+        let config = TaskRandConfig::parse("[preview_urgency_coefficients]\ndue = 12.0").unwrap();
+
+        assert_eq!(
+            config.preview_urgency_coefficients,
+            BTreeMap::from([("due".to_owned(), 12.0)])
+        );
+        assert_eq!(
+            config.urgency_coefficients,
+            TaskRandConfig::default().urgency_coefficients
+        );
+    }
+
+    #[test]
+    fn parse_profiles() {
+        let config = TaskRandConfig::parse(
+            "[profiles.work]\n\
+             taskrc = '/home/me/.taskrc-work'\n\
+             filters = ['+work']\n\
+             urgency_coefficients = { age = 1.0 }",
+        )
+        .unwrap();
+
+        let work = config.profile(Some("work")).unwrap();
+        assert_eq!(work.taskrc, Some(PathBuf::from("/home/me/.taskrc-work")));
+        assert_eq!(work.filters, vec!["+work"]);
+        assert_eq!(work.urgency_coefficients.get("age"), Some(&1.0));
+    }
+
+    #[test]
+    fn missing_profile_is_an_error() {
+        assert!(TaskRandConfig::default().profile(Some("work")).is_err());
+    }
+
+    #[test]
+    fn no_profile_uses_default() {
+        let config = TaskRandConfig::parse("[profiles.default]\ncontext = 'home'").unwrap();
+
+        assert_eq!(
+            config.profile(None).unwrap().context,
+            Some("home".to_owned())
+        );
+        assert_eq!(
+            TaskRandConfig::default().profile(None).unwrap(),
+            Profile::default()
+        );
+    }
+}
@@ -1,23 +1,113 @@
 use crate::{config::Config, task::Task};
 use anyhow::{Context, Result};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
 use tokio::process::Command;
 
 #[derive(Debug)]
 pub struct Taskwarrior {
     pub binary: PathBuf,
+
+    /// An alternative `.taskrc` to use instead of the default one.
+    pub taskrc: Option<PathBuf>,
+
+    /// An alternative data directory to use instead of the one configured in
+    /// the `.taskrc`.
+    pub data_location: Option<PathBuf>,
+
+    /// A Taskwarrior context to apply when exporting tasks.
+    pub context: Option<String>,
+
+    /// How many more times to try exports and modifications that fail
+    /// because the database is locked.
+    pub retries: u32,
 }
 
 impl Taskwarrior {
     pub fn new(binary: PathBuf) -> Self {
-        Self { binary }
+        Self {
+            binary,
+            taskrc: None,
+            data_location: None,
+            context: None,
+            retries: 0,
+        }
+    }
+
+    pub fn with_taskrc(mut self, taskrc: Option<PathBuf>) -> Self {
+        self.taskrc = taskrc;
+
+        self
+    }
+
+    pub fn with_data_location(mut self, data_location: Option<PathBuf>) -> Self {
+        self.data_location = data_location;
+
+        self
+    }
+
+    pub fn with_context(mut self, context: Option<String>) -> Self {
+        self.context = context;
+
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+
+        self
+    }
+
+    /// Start a command for the Taskwarrior binary, including any overrides
+    /// that need to apply to every call.
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.binary);
+
+        if let Some(taskrc) = &self.taskrc {
+            let mut arg = std::ffi::OsString::from("rc:");
+            arg.push(taskrc);
+            command.arg(arg);
+        }
+
+        if let Some(data_location) = &self.data_location {
+            let mut arg = std::ffi::OsString::from("rc.data.location=");
+            arg.push(data_location);
+            command.arg(arg);
+        }
+
+        command
+    }
+
+    /// Environment variables that point other programs that call `task`
+    /// (like `tw-breakdown`) at the same `.taskrc` and data as we use.
+    pub fn env(&self) -> Vec<(&'static str, &Path)> {
+        let mut env = Vec::new();
+
+        if let Some(taskrc) = &self.taskrc {
+            env.push(("TASKRC", taskrc.as_path()));
+        }
+
+        if let Some(data_location) = &self.data_location {
+            env.push(("TASKDATA", data_location.as_path()));
+        }
+
+        env
     }
 
     #[tracing::instrument]
     pub fn export(&self) -> ExportBuilder {
+        let mut command = self.command();
+
+        if let Some(context) = &self.context {
+            command.arg(format!("rc.context={}", context));
+        }
+
         ExportBuilder {
-            binary: self.binary.clone(),
+            command,
+            retries: self.retries,
             filters: Vec::new(),
             urgency_coefficients: HashMap::new(),
         }
@@ -25,7 +115,7 @@ impl Taskwarrior {
 
     #[tracing::instrument]
     pub async fn config(&self) -> Result<Config> {
-        let mut command = Command::new(&self.binary);
+        let mut command = self.command();
         command.arg("_show");
 
         tracing::trace!(?command, "getting config from taskwarrior");
@@ -41,8 +131,40 @@ impl Taskwarrior {
         Config::parse(&config_text).context("could not parse config")
     }
 
+    #[tracing::instrument]
+    pub async fn contexts(&self) -> Result<Vec<String>> {
+        let mut command = self.command();
+        command.arg("_context");
+
+        tracing::trace!(?command, "getting contexts from taskwarrior");
+
+        let output = command
+            .output()
+            .await
+            .context("could not call Taskwarrior")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "could not list contexts. Exit code {:?}\n\nStdout:\n{}\n\nStderr:\n{}",
+                output.status,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let contexts_text =
+            String::from_utf8(output.stdout).context("contexts contained invalid UTF-8")?;
+
+        Ok(contexts_text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_owned)
+            .collect())
+    }
+
     pub fn mark_done_command(&self, id: &str) -> Command {
-        let mut command = Command::new(&self.binary);
+        let mut command = self.command();
         command.args([id, "done"]);
 
         command
@@ -71,10 +193,32 @@ impl Taskwarrior {
         Ok(())
     }
 
+    #[tracing::instrument]
+    pub async fn annotate(&self, id: &str, note: &str) -> Result<()> {
+        let mut command = self.command();
+        command.args([id, "annotate", "--", note]);
+
+        tracing::trace!(?command, "annotating task");
+
+        let out = command.output().await.context("could not annotate task")?;
+
+        if !out.status.success() {
+            return Err(anyhow::anyhow!(
+                "could not annotate task. Exit code {:?}\n\nStdout:\n{}\n\nStderr:\n{}",
+                out.status,
+                String::from_utf8_lossy(&out.stdout),
+                String::from_utf8_lossy(&out.stderr)
+            ));
+        }
+
+        Ok(())
+    }
+
     #[tracing::instrument]
     pub fn modify(&self) -> ModifyBuilder {
         ModifyBuilder {
-            binary: self.binary.clone(),
+            command: self.command(),
+            retries: self.retries,
             subjects: Vec::new(),
             mods: Vec::new(),
         }
@@ -82,7 +226,8 @@ impl Taskwarrior {
 }
 
 pub struct ExportBuilder {
-    binary: PathBuf,
+    command: Command,
+    retries: u32,
     filters: Vec<String>,
     urgency_coefficients: HashMap<String, f64>,
 }
@@ -102,26 +247,55 @@ impl ExportBuilder {
 
     #[tracing::instrument("export", skip(self))]
     pub async fn call(self) -> Result<Vec<Task>> {
-        let mut command = Command::new(self.binary);
+        let mut command = self.command;
 
         for (key, coefficient) in self.urgency_coefficients {
             command.arg(format!("rc.urgency.{}.coefficient={}", key, coefficient));
         }
 
+        // Ask for one task per line so we can parse as we read instead of
+        // holding the whole export in memory first.
+        command.arg("rc.json.array=off");
+
         command.args(self.filters);
 
         command.arg("export");
 
         tracing::trace!(?command, "calling taskwarrior for export");
 
-        let output = command.output().await.context("could not retrieve tasks")?;
+        let mut attempt = 0;
+
+        loop {
+            let export = stream_export(&mut command)
+                .await
+                .context("could not retrieve tasks")?;
+
+            // If we couldn't parse what Taskwarrior gave us, that's the more
+            // useful error even if it also failed.
+            let tasks = export.tasks?;
+
+            if export.status.success() {
+                return Ok(tasks);
+            }
 
-        serde_json::from_slice(&output.stdout).context("could not deserialize tasks")
+            if attempt < self.retries && is_transient(&export.stderr) {
+                attempt += 1;
+                back_off(attempt, &export.stderr).await;
+                continue;
+            }
+
+            return Err(anyhow::anyhow!(
+                "could not export tasks. Exit code {:?}\n\nStderr:\n{}",
+                export.status,
+                String::from_utf8_lossy(&export.stderr)
+            ));
+        }
     }
 }
 
 pub struct ModifyBuilder {
-    binary: PathBuf,
+    command: Command,
+    retries: u32,
     subjects: Vec<String>,
     mods: Vec<String>,
 }
@@ -140,7 +314,7 @@ impl ModifyBuilder {
     }
 
     pub fn command(self) -> Command {
-        let mut command = Command::new(self.binary);
+        let mut command = self.command;
 
         command.args(self.subjects);
         command.arg("modify");
@@ -151,11 +325,14 @@ impl ModifyBuilder {
 
     #[tracing::instrument("modify", skip(self))]
     pub async fn call(self) -> Result<()> {
+        let retries = self.retries;
         let mut command = self.command();
 
         tracing::trace!(?command, "calling taskwarrior for modify");
 
-        let out = command.output().await.context("could not modify tasks")?;
+        let out = output_with_retries(&mut command, retries)
+            .await
+            .context("could not modify tasks")?;
 
         if !out.status.success() {
             return Err(anyhow::anyhow!(
@@ -169,3 +346,292 @@ impl ModifyBuilder {
         Ok(())
     }
 }
+
+/// How long to wait before the first retry. Each retry after that waits a
+/// little longer.
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Run `command`, trying again up to `retries` times if Taskwarrior fails in a
+/// way that should clear up on its own (e.g. a sync hook holding the lock.)
+/// Any other failure is returned right away for the caller to report.
+async fn output_with_retries(command: &mut Command, retries: u32) -> std::io::Result<Output> {
+    let mut attempt = 0;
+
+    loop {
+        let output = command.output().await?;
+
+        if output.status.success() || attempt >= retries || !is_transient(&output.stderr) {
+            return Ok(output);
+        }
+
+        attempt += 1;
+        back_off(attempt, &output.stderr).await;
+    }
+}
+
+async fn back_off(attempt: u32, stderr: &[u8]) {
+    tracing::warn!(
+        attempt,
+        stderr = %String::from_utf8_lossy(stderr),
+        "taskwarrior failed with what looks like a lock, retrying"
+    );
+
+    tokio::time::sleep(RETRY_BACKOFF * attempt).await;
+}
+
+struct Export {
+    status: ExitStatus,
+    tasks: Result<Vec<Task>>,
+    stderr: Vec<u8>,
+}
+
+/// Run an export, parsing tasks as they come in on stdout. The tasks are
+/// only meaningful if the command succeeded.
+async fn stream_export(command: &mut Command) -> Result<Export> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("could not start taskwarrior")?;
+
+    let stdout = child.stdout.take().context("stdout was not piped")?;
+    let mut stderr = child.stderr.take().context("stderr was not piped")?;
+
+    let read_tasks = async {
+        let mut lines = BufReader::new(stdout).lines();
+        let mut tasks = Ok(Vec::new());
+
+        while let Some(line) = lines.next_line().await? {
+            // With `json.array=off` we get one task per line, but some
+            // versions wrap them in an array anyway (one task per line,
+            // separated by commas.) Either way, each task is on its own line.
+            let line = line.trim().trim_end_matches(',');
+            if matches!(line, "" | "[" | "]" | "[]") {
+                continue;
+            }
+
+            // Keep reading after a bad line. If we stopped, Taskwarrior would
+            // fail writing to a closed pipe and we'd report that instead.
+            if let Ok(parsed) = &mut tasks {
+                match serde_json::from_str(line) {
+                    Ok(task) => parsed.push(task),
+                    Err(err) => tasks = Err(anyhow::Error::new(err)),
+                }
+            }
+        }
+
+        tasks.context("could not deserialize task")
+    };
+
+    let read_stderr = async {
+        let mut buf = Vec::new();
+        stderr.read_to_end(&mut buf).await.map(|_| buf)
+    };
+
+    let (tasks, stderr) = tokio::join!(read_tasks, read_stderr);
+    let stderr = stderr.context("could not read stderr")?;
+
+    let status = child
+        .wait()
+        .await
+        .context("could not wait for taskwarrior")?;
+
+    Ok(Export {
+        status,
+        tasks,
+        stderr,
+    })
+}
+
+/// Whether Taskwarrior failed because something else had the database
+/// locked. We look for whole words so that e.g. "blocked" doesn't count.
+fn is_transient(stderr: &[u8]) -> bool {
+    String::from_utf8_lossy(stderr)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word == "lock" || word == "locked")
+}
+
+/// Write a stand-in for the `task` binary that runs `script`.
+#[cfg(test)]
+pub fn shim(dir: &tempfile::TempDir, script: &str) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.path().join("task");
+
+    std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    path
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn is_transient_lock_messages() {
+        assert!(is_transient(b"database is locked"));
+        assert!(is_transient(b"Could not acquire lock."));
+    }
+
+    #[test]
+    fn is_transient_ignores_blocking() {
+        assert!(!is_transient(b"Task 3 is blocked by task 2."));
+        assert!(!is_transient(b"This task is blocking others."));
+    }
+
+    #[tokio::test]
+    async fn export_failure_includes_stderr() {
+        let dir = tempfile::tempdir().unwrap();
+        let tw = Taskwarrior::new(shim(&dir, "echo 'database is locked' >&2; exit 1"));
+
+        let err = tw.export().call().await.unwrap_err();
+
+        assert!(err.to_string().contains("database is locked"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn export_success_parses_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let tw = Taskwarrior::new(shim(&dir, "echo '[]'"));
+
+        assert!(tw.export().call().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn export_reports_tasks_it_could_not_parse() {
+        let dir = tempfile::tempdir().unwrap();
+        let tw = Taskwarrior::new(shim(
+            &dir,
+            "echo 'not a task'; echo '{\"id\":1}'; echo 'some error' >&2; exit 1",
+        ));
+
+        let err = tw.export().call().await.unwrap_err();
+
+        assert_eq!(err.to_string(), "could not deserialize task");
+    }
+
+    #[tokio::test]
+    async fn export_retries_when_locked() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("ran");
+        let tw = Taskwarrior::new(shim(
+            &dir,
+            &format!(
+                "if [ -e {0} ]; then echo '[]'; else touch {0}; echo 'database is locked' >&2; exit 1; fi",
+                marker.display()
+            ),
+        ))
+        .with_retries(1);
+
+        assert!(tw.export().call().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn modify_does_not_retry_permanent_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let calls = dir.path().join("calls");
+        let tw = Taskwarrior::new(shim(
+            &dir,
+            &format!(
+                "echo >> {}; echo 'No matches.' >&2; exit 1",
+                calls.display()
+            ),
+        ))
+        .with_retries(3);
+
+        assert!(tw.modify().with_subject("1").call().await.is_err());
+        assert_eq!(std::fs::read_to_string(calls).unwrap().lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn export_reads_one_task_per_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let task = r#"{"id":1,"uuid":"a","description":"a task","urgency":1.0,"entry":"20250101T000000Z"}"#;
+        let tw = Taskwarrior::new(shim(&dir, &format!("yes '{}' | head -n 20000", task)));
+
+        assert_eq!(tw.export().call().await.unwrap().len(), 20000);
+    }
+
+    /// A rough benchmark of streaming against reading the whole export
+    /// before parsing it, which is what we used to do. Run it with `cargo
+    /// test --release -- --ignored --nocapture export_benchmark`.
+    #[tokio::test]
+    #[ignore]
+    async fn export_benchmark() {
+        const TASKS: usize = 200_000;
+
+        let dir = tempfile::tempdir().unwrap();
+        let task = r#"{"id":1,"uuid":"00000000-0000-0000-0000-000000000000","description":"a task with a reasonably long description","project":"work.backend","tags":["one","two"],"urgency":4.2,"entry":"20250101T000000Z","annotations":[{"entry":"20250101T000000Z","description":"see https://example.com"}]}"#;
+        let export = dir.path().join("export");
+        std::fs::write(&export, format!("{}\n", task).repeat(TASKS)).unwrap();
+        let tw = Taskwarrior::new(shim(&dir, &format!("cat {}", export.display())));
+
+        let start = std::time::Instant::now();
+        let streamed = tw.export().call().await.unwrap();
+        let streaming = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let output = Command::new(&tw.binary).output().await.unwrap();
+        let buffered: Vec<Task> = output
+            .stdout
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        let buffering = start.elapsed();
+
+        assert_eq!(streamed.len(), TASKS);
+        assert_eq!(buffered.len(), TASKS);
+
+        println!(
+            "{} tasks: streaming {:?}, buffering {:?} (holding {} bytes of stdout)",
+            TASKS,
+            streaming,
+            buffering,
+            output.stdout.len()
+        );
+    }
+
+    #[tokio::test]
+    async fn export_reads_an_array() {
+        let dir = tempfile::tempdir().unwrap();
+        let task = r#"{"id":1,"uuid":"a","description":"a task","urgency":1.0,"entry":"20250101T000000Z"}"#;
+        let tw = Taskwarrior::new(shim(
+            &dir,
+            &format!("echo '['; echo '{0},'; echo '{0}'; echo ']'", task),
+        ));
+
+        assert_eq!(tw.export().call().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn modify_failure_includes_stderr() {
+        let dir = tempfile::tempdir().unwrap();
+        let tw = Taskwarrior::new(shim(
+            &dir,
+            "echo \"'someday' is not a valid date.\" >&2; exit 2",
+        ));
+
+        let err = tw
+            .modify()
+            .with_subject("1")
+            .with_mod("wait:someday")
+            .call()
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("is not a valid date"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn mark_done_failure_includes_stderr() {
+        let dir = tempfile::tempdir().unwrap();
+        let tw = Taskwarrior::new(shim(&dir, "echo 'Task 1 is not pending.' >&2; exit 1"));
+
+        let err = tw.mark_done("1").await.unwrap_err();
+
+        assert!(err.to_string().contains("is not pending"), "{}", err);
+    }
+}
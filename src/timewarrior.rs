@@ -0,0 +1,55 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use tokio::process::Command;
+
+/// Timewarrior, for people who keep their time log there instead of (or as
+/// well as) in Taskwarrior.
+#[derive(Debug)]
+pub struct Timewarrior {
+    pub binary: PathBuf,
+}
+
+impl Timewarrior {
+    pub fn new(binary: PathBuf) -> Self {
+        Self { binary }
+    }
+
+    /// Start tracking time with the given tags. Timewarrior stops whatever
+    /// was being tracked before, so there's no need to call `stop` first.
+    #[tracing::instrument]
+    pub async fn start(&self, tags: &[String]) -> Result<()> {
+        let mut command = Command::new(&self.binary);
+        command.arg("start").args(tags);
+
+        self.run(command, "start").await
+    }
+
+    #[tracing::instrument]
+    pub async fn stop(&self) -> Result<()> {
+        let mut command = Command::new(&self.binary);
+        command.arg("stop");
+
+        self.run(command, "stop").await
+    }
+
+    async fn run(&self, mut command: Command, what: &str) -> Result<()> {
+        tracing::trace!(?command, "calling timewarrior");
+
+        let out = command
+            .output()
+            .await
+            .with_context(|| format!("could not {} timewarrior", what))?;
+
+        if !out.status.success() {
+            return Err(anyhow::anyhow!(
+                "could not {} timewarrior. Exit code {:?}\n\nStdout:\n{}\n\nStderr:\n{}",
+                what,
+                out.status,
+                String::from_utf8_lossy(&out.stdout),
+                String::from_utf8_lossy(&out.stderr)
+            ));
+        }
+
+        Ok(())
+    }
+}
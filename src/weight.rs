@@ -0,0 +1,216 @@
+use crate::config::Config;
+use crate::task::Task;
+use crate::task_rand_config::TaskRandConfig;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Everything besides the task itself that a strategy might weigh.
+pub struct WeightContext<'a> {
+    pub now: DateTime<Utc>,
+    pub config: &'a Config,
+    pub settings: &'a TaskRandConfig,
+    pub recently_shown: &'a HashMap<String, DateTime<Utc>>,
+    pub skipped: &'a HashMap<String, DateTime<Utc>>,
+    pub last_project: Option<&'a str>,
+}
+
+/// One step in working out how likely a task is to be picked. A task's
+/// weight is the product of every strategy in `pipeline`.
+pub trait WeightStrategy {
+    fn multiplier(&self, task: &Task, context: &WeightContext) -> f64;
+}
+
+/// The names we accept for strategies in the config file.
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Weight {
+    Urgency,
+    Recency,
+    Skipped,
+    DueBoost,
+    SpreadProjects,
+}
+
+impl Weight {
+    pub fn strategy(self) -> &'static dyn WeightStrategy {
+        match self {
+            Self::Urgency => &Urgency,
+            Self::Recency => &Recency,
+            Self::Skipped => &Skipped,
+            Self::DueBoost => &DueBoost,
+            Self::SpreadProjects => &SpreadProjects,
+        }
+    }
+}
+
+/// Just Taskwarrior's urgency. Everything else has to be asked for.
+pub fn default_weights() -> Vec<Weight> {
+    vec![Weight::Urgency]
+}
+
+/// The strategies to run, in order: `settings.weights`, then `due_boost`
+/// and `spread_projects` if their own settings turn them on and they aren't
+/// listed already.
+pub fn pipeline(settings: &TaskRandConfig) -> Vec<Weight> {
+    let mut weights = settings.weights.clone();
+
+    let implied = [
+        (Weight::DueBoost, settings.due_boost_factor != 1.0),
+        (Weight::SpreadProjects, settings.spread_projects),
+    ];
+
+    for (weight, on) in implied {
+        if on && !weights.contains(&weight) {
+            weights.push(weight);
+        }
+    }
+
+    weights
+}
+
+/// Taskwarrior's urgency, as of now. Negative urgency can't be picked.
+struct Urgency;
+
+impl WeightStrategy for Urgency {
+    fn multiplier(&self, task: &Task, context: &WeightContext) -> f64 {
+        task.urgency_at(context.now, context.config).max(0.0)
+    }
+}
+
+/// Make tasks we've just shown less likely to come right back.
+struct Recency;
+
+impl WeightStrategy for Recency {
+    fn multiplier(&self, task: &Task, context: &WeightContext) -> f64 {
+        context.recently_shown.get(&task.uuid).map_or(1.0, |shown| {
+            recency_factor(context.now - *shown, context.settings.recent_half_life)
+        })
+    }
+}
+
+/// Like `Recency`, but tasks we skipped come back even more slowly.
+struct Skipped;
+
+impl WeightStrategy for Skipped {
+    fn multiplier(&self, task: &Task, context: &WeightContext) -> f64 {
+        context.skipped.get(&task.uuid).map_or(1.0, |skipped| {
+            recency_factor(
+                context.now - *skipped,
+                context.settings.recent_half_life * SKIPPED_HALF_LIFE_FACTOR,
+            )
+        })
+    }
+}
+
+/// Favor tasks due within `settings.due_boost_window`.
+struct DueBoost;
+
+impl WeightStrategy for DueBoost {
+    fn multiplier(&self, task: &Task, context: &WeightContext) -> f64 {
+        match task.due {
+            Some(due) if due - context.now <= context.settings.due_boost_window => {
+                context.settings.due_boost_factor
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+/// Disfavor the last task's project when `settings.spread_projects` is on.
+struct SpreadProjects;
+
+impl WeightStrategy for SpreadProjects {
+    fn multiplier(&self, task: &Task, context: &WeightContext) -> f64 {
+        if context.settings.spread_projects
+            && task.project.is_some()
+            && task.project.as_deref() == context.last_project
+        {
+            SAME_PROJECT_FACTOR
+        } else {
+            1.0
+        }
+    }
+}
+
+/// How much to scale a task's weight given how long ago we last picked it.
+/// This recovers towards 1 with the given half-life, but never reaches 0 so
+/// that every task stays reachable.
+fn recency_factor(since_shown: Duration, half_life: Duration) -> f64 {
+    if half_life <= Duration::zero() {
+        return 1.0;
+    }
+
+    let half_lives = since_shown.num_seconds().max(0) as f64 / half_life.num_seconds() as f64;
+
+    (1.0 - 0.5_f64.powf(half_lives)).max(MIN_RECENCY_FACTOR)
+}
+
+const MIN_RECENCY_FACTOR: f64 = 0.01;
+
+/// How many times longer than `recent_half_life` a skipped task takes to get
+/// back half its chance of being picked.
+const SKIPPED_HALF_LIFE_FACTOR: i32 = 4;
+
+/// How much less likely a task is to be picked when it's in the same project
+/// as the last one and `settings.spread_projects` is on.
+pub const SAME_PROJECT_FACTOR: f64 = 0.25;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pipeline_defaults_to_urgency() {
+        assert_eq!(pipeline(&TaskRandConfig::default()), vec![Weight::Urgency]);
+    }
+
+    #[test]
+    fn pipeline_adds_strategies_turned_on_by_settings() {
+        let settings = TaskRandConfig {
+            due_boost_factor: 2.0,
+            spread_projects: true,
+            ..TaskRandConfig::default()
+        };
+
+        assert_eq!(
+            pipeline(&settings),
+            vec![Weight::Urgency, Weight::DueBoost, Weight::SpreadProjects]
+        );
+    }
+
+    #[test]
+    fn pipeline_does_not_repeat_listed_strategies() {
+        let settings = TaskRandConfig {
+            spread_projects: true,
+            weights: vec![Weight::SpreadProjects, Weight::Urgency],
+            ..TaskRandConfig::default()
+        };
+
+        assert_eq!(
+            pipeline(&settings),
+            vec![Weight::SpreadProjects, Weight::Urgency]
+        );
+    }
+
+    #[test]
+    fn recency_factor_just_shown_is_not_zero() {
+        assert_eq!(
+            recency_factor(Duration::zero(), Duration::minutes(30)),
+            MIN_RECENCY_FACTOR
+        );
+    }
+
+    #[test]
+    fn recency_factor_after_one_half_life() {
+        assert_eq!(
+            recency_factor(Duration::minutes(30), Duration::minutes(30)),
+            0.5
+        );
+    }
+
+    #[test]
+    fn recency_factor_disabled() {
+        assert_eq!(recency_factor(Duration::zero(), Duration::zero()), 1.0);
+    }
+}